@@ -0,0 +1,58 @@
+//! Audit events emitted by the write operations of a `SystemdBootConf`.
+//!
+//! Set a hook with `SystemdBootConf::set_audit_hook()` to get notified of every file created,
+//! modified, or deleted by the library.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsdbootconf::SystemdBootConf;
+//!
+//! let mut systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+//!
+//! systemd_boot_conf.set_audit_hook(Box::new(|event| {
+//!     println!("{:?} {}", event.action, event.path.display());
+//! }));
+//! systemd_boot_conf.write_all().unwrap();
+//! ```
+
+use std::{fmt, path::PathBuf};
+
+/// The kind of change made to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// The file did not exist and has been created.
+    Create,
+    /// The file existed and has been overwritten.
+    Modify,
+    /// The file has been deleted.
+    Delete,
+}
+
+/// A change made to a file by a write operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    /// The kind of change.
+    pub action: AuditAction,
+    /// The path of the changed file.
+    pub path: PathBuf,
+}
+
+impl AuditEvent {
+    /// Create a new `AuditEvent`.
+    pub fn new<P: Into<PathBuf>>(action: AuditAction, path: P) -> Self {
+        Self {
+            action,
+            path: path.into(),
+        }
+    }
+}
+
+/// A callback invoked with every `AuditEvent`.
+pub(crate) struct AuditHook(pub(crate) Box<dyn Fn(&AuditEvent)>);
+
+impl fmt::Debug for AuditHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuditHook")
+    }
+}
@@ -17,59 +17,291 @@
 //! assert_eq!(config.to_string(), built.to_string());
 //! ```
 
-use std::{fs, ops::Not, path::Path, str::FromStr};
+use std::{collections::BTreeMap, fmt, fs, io::Read, path::Path, str::FromStr};
 
-use crate::{generate_builder_method, Entry, LibSDBootConfError};
+use crate::{
+    generate_builder_method, parser,
+    report::{ParseReport, ParseWarning},
+    util, Entry, LibSDBootConfError,
+};
+
+/// How long to show the boot menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Timeout {
+    /// Show the menu for the given number of seconds before booting the default entry.
+    Seconds(u32),
+    /// `menu-force`: show the menu indefinitely until an entry is selected.
+    MenuForce,
+    /// `menu-hidden`: boot the default entry immediately, the menu is shown on a key press.
+    MenuHidden,
+    /// `menu-disabled`: boot the default entry immediately, the menu cannot be shown.
+    MenuDisabled,
+}
+
+impl From<u32> for Timeout {
+    fn from(seconds: u32) -> Self {
+        Self::Seconds(seconds)
+    }
+}
+
+impl FromStr for Timeout {
+    type Err = LibSDBootConfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "menu-force" => Self::MenuForce,
+            "menu-hidden" => Self::MenuHidden,
+            "menu-disabled" => Self::MenuDisabled,
+            _ => Self::Seconds(
+                s.parse()
+                    .map_err(|_| LibSDBootConfError::ConfigParseError)?,
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Seconds(seconds) => write!(f, "{}", seconds),
+            Self::MenuForce => f.write_str("menu-force"),
+            Self::MenuHidden => f.write_str("menu-hidden"),
+            Self::MenuDisabled => f.write_str("menu-disabled"),
+        }
+    }
+}
+
+/// Console mode of the boot menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConsoleMode {
+    /// `auto`: pick a suitable mode automatically.
+    Auto,
+    /// `max`: pick the highest-numbered available mode.
+    Max,
+    /// `keep`: keep the mode selected by the firmware.
+    Keep,
+    /// A mode number as reported by the firmware.
+    Mode(u32),
+}
+
+impl FromStr for ConsoleMode {
+    type Err = LibSDBootConfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "auto" => Self::Auto,
+            "max" => Self::Max,
+            "keep" => Self::Keep,
+            _ => Self::Mode(
+                s.parse()
+                    .map_err(|_| LibSDBootConfError::ConfigParseError)?,
+            ),
+        })
+    }
+}
+
+impl fmt::Display for ConsoleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Max => f.write_str("max"),
+            Self::Keep => f.write_str("keep"),
+            Self::Mode(mode) => write!(f, "{}", mode),
+        }
+    }
+}
+
+/// How the random seed file on the ESP is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RandomSeedMode {
+    /// `off`: do not use the random seed file.
+    Off,
+    /// `with-system-token`: use the random seed file only if a system token is set.
+    WithSystemToken,
+    /// `always`: always use the random seed file.
+    Always,
+}
+
+impl FromStr for RandomSeedMode {
+    type Err = LibSDBootConfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "with-system-token" => Ok(Self::WithSystemToken),
+            "always" => Ok(Self::Always),
+            _ => Err(LibSDBootConfError::ConfigParseError),
+        }
+    }
+}
+
+impl fmt::Display for RandomSeedMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => f.write_str("off"),
+            Self::WithSystemToken => f.write_str("with-system-token"),
+            Self::Always => f.write_str("always"),
+        }
+    }
+}
+
+/// Whether Secure Boot keys found on the ESP are enrolled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SecureBootEnroll {
+    /// `off`: never enroll the keys.
+    Off,
+    /// `manual`: show an entry to enroll the keys manually.
+    Manual,
+    /// `if-safe`: enroll the keys automatically if it is considered safe.
+    IfSafe,
+    /// `force`: always enroll the keys automatically.
+    Force,
+}
+
+impl FromStr for SecureBootEnroll {
+    type Err = LibSDBootConfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "manual" => Ok(Self::Manual),
+            "if-safe" => Ok(Self::IfSafe),
+            "force" => Ok(Self::Force),
+            _ => Err(LibSDBootConfError::ConfigParseError),
+        }
+    }
+}
+
+impl fmt::Display for SecureBootEnroll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => f.write_str("off"),
+            Self::Manual => f.write_str("manual"),
+            Self::IfSafe => f.write_str("if-safe"),
+            Self::Force => f.write_str("force"),
+        }
+    }
+}
 
 /// A systemd-boot loader configuration.
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     /// Pattern to select the default entry in the list of entries.
     pub default: Option<String>,
-    /// Timeout in seconds for how long to show the menu.
-    pub timeout: Option<u32>,
+    /// How long to show the menu.
+    ///
+    /// `None` omits the `timeout` line, leaving the behavior to systemd-boot (or to the timeout
+    /// set in the boot menu). `Some(Timeout::Seconds(0))` boots the default entry immediately,
+    /// while still showing the menu on a key press.
+    pub timeout: Option<Timeout>,
+    /// Console mode of the menu.
+    pub console_mode: Option<ConsoleMode>,
+    /// Whether the kernel command line editor is enabled.
+    pub editor: Option<bool>,
+    /// Whether entries for other boot loaders found on the ESP are shown.
+    pub auto_entries: Option<bool>,
+    /// Whether an entry for rebooting into the firmware setup is shown.
+    pub auto_firmware: Option<bool>,
+    /// Whether to beep when the menu is shown.
+    pub beep: Option<bool>,
+    /// How the random seed file on the ESP is used.
+    pub random_seed_mode: Option<RandomSeedMode>,
+    /// Whether Secure Boot keys found on the ESP are enrolled.
+    pub secure_boot_enroll: Option<SecureBootEnroll>,
+    /// Other keys not modeled by `Config`, preserved as-is.
+    ///
+    /// Like the other keys, the keys are matched case-insensitively when parsing, and are stored
+    /// and written in lowercase. The values keep their case.
+    pub extra: BTreeMap<String, String>,
 }
 
 impl FromStr for Config {
     type Err = LibSDBootConfError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut config = Self::default();
-        let lines = s.lines();
+        Self::parse(s, None)
+    }
+}
 
-        for line in lines {
-            if line.starts_with('#') || line.is_empty() {
-                continue;
-            }
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(default) = &self.default {
+            writeln!(f, "default {}", default)?;
+        }
 
-            let mut parts = line.splitn(2, ' ');
-            let key = parts.next().ok_or(LibSDBootConfError::ConfigParseError)?;
-            let value = parts.next().ok_or(LibSDBootConfError::ConfigParseError)?;
+        if let Some(timeout) = &self.timeout {
+            writeln!(f, "timeout {}", timeout)?;
+        }
 
-            match key {
-                "default" => config.default = Some(value.to_string()),
-                "timeout" => config.timeout = Some(value.parse().unwrap_or_default()),
-                _ => continue,
-            }
+        if let Some(console_mode) = &self.console_mode {
+            writeln!(f, "console-mode {}", console_mode)?;
         }
 
-        Ok(config)
-    }
-}
+        if let Some(editor) = self.editor {
+            writeln!(f, "editor {}", format_bool(editor))?;
+        }
 
-impl ToString for Config {
-    fn to_string(&self) -> String {
-        let mut buffer = String::new();
+        if let Some(auto_entries) = self.auto_entries {
+            writeln!(f, "auto-entries {}", format_bool(auto_entries))?;
+        }
 
-        if let Some(default) = &self.default {
-            buffer.push_str(&format!("default {}\n", default));
+        if let Some(auto_firmware) = self.auto_firmware {
+            writeln!(f, "auto-firmware {}", format_bool(auto_firmware))?;
         }
 
-        if let Some(timeout) = &self.timeout {
-            buffer.push_str(&format!("timeout {}\n", timeout));
+        if let Some(beep) = self.beep {
+            writeln!(f, "beep {}", format_bool(beep))?;
+        }
+
+        if let Some(random_seed_mode) = &self.random_seed_mode {
+            writeln!(f, "random-seed-mode {}", random_seed_mode)?;
+        }
+
+        if let Some(secure_boot_enroll) = &self.secure_boot_enroll {
+            writeln!(f, "secure-boot-enroll {}", secure_boot_enroll)?;
+        }
+
+        for (key, value) in &self.extra {
+            writeln!(f, "{} {}", key, value)?;
         }
 
-        buffer
+        Ok(())
+    }
+}
+
+/// Keys modeled by `Config`.
+const KEYS: &[&str] = &[
+    "default",
+    "timeout",
+    "console-mode",
+    "editor",
+    "auto-entries",
+    "auto-firmware",
+    "beep",
+    "random-seed-mode",
+    "secure-boot-enroll",
+];
+
+/// Parse a boolean value the way systemd-boot does.
+fn parse_bool(value: &str) -> Result<bool, LibSDBootConfError> {
+    match value {
+        "1" | "yes" | "y" | "true" | "t" | "on" => Ok(true),
+        "0" | "no" | "n" | "false" | "f" | "off" => Ok(false),
+        _ => Err(LibSDBootConfError::ConfigParseError),
+    }
+}
+
+/// Format a boolean value as `yes` or `no`.
+fn format_bool(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
     }
 }
 
@@ -79,24 +311,196 @@ impl Config {
     /// # Examples
     ///
     /// ```
-    /// use libsdbootconf::config::Config;
+    /// use libsdbootconf::config::{Config, Timeout};
     ///
     /// let config = Config::new(Some("5.12.0-aosc-main"), Some(5u32));
     ///
     /// assert_eq!(config.default, Some("5.12.0-aosc-main".to_owned()));
-    /// assert_eq!(config.timeout, Some(5u32));
+    /// assert_eq!(config.timeout, Some(Timeout::Seconds(5)));
     /// ```
     pub fn new<S, U>(default: Option<S>, timeout: Option<U>) -> Config
     where
         S: Into<String>,
-        U: Into<u32>,
+        U: Into<Timeout>,
     {
         Config {
             default: default.map(|s| s.into()),
             timeout: timeout.map(|u| u.into()),
+            ..Default::default()
         }
     }
 
+    /// Parse a config from a string like `Config::from_str()`, mentioning where it has been read
+    /// from on error.
+    ///
+    /// Errors are wrapped in `LibSDBootConfError::ParseErrorIn` with `source` as the path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::config::Config;
+    /// use std::path::Path;
+    ///
+    /// let error = Config::parse_named("timeout\n", Path::new("/efi/loader/loader.conf")).unwrap_err();
+    ///
+    /// assert!(error.to_string().contains("/efi/loader/loader.conf"));
+    /// ```
+    pub fn parse_named(text: &str, source: &Path) -> Result<Config, LibSDBootConfError> {
+        Config::from_str(text).map_err(|error| LibSDBootConfError::ParseErrorIn {
+            path: source.to_owned(),
+            source: Box::new(error),
+        })
+    }
+
+    /// Parse a config from a string like `Config::from_str()`, but collect the problems found
+    /// as warnings instead of failing.
+    ///
+    /// Lines that fail to parse are skipped. Unknown keys are kept in `extra` and reported, as
+    /// well as keys set more than once, in which case the last value is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{report::ParseWarning, Config};
+    ///
+    /// let report = Config::parse_with_report("timeout soon\ndefault aosc.conf\n");
+    ///
+    /// assert_eq!(report.value.default, Some("aosc.conf".to_owned()));
+    /// assert!(matches!(report.warnings[0], ParseWarning::InvalidValue { line: 1, .. }));
+    /// ```
+    pub fn parse_with_report(s: &str) -> ParseReport<Config> {
+        let mut warnings = Vec::new();
+        let value = Self::parse(s, Some(&mut warnings)).unwrap_or_default();
+
+        ParseReport { value, warnings }
+    }
+
+    /// Parse a config, collecting the problems found into `warnings` instead of failing if given.
+    fn parse(
+        s: &str,
+        mut warnings: Option<&mut Vec<ParseWarning>>,
+    ) -> Result<Config, LibSDBootConfError> {
+        let mut config = Self::default();
+        let mut seen = Vec::new();
+        let s = s.strip_prefix('\u{feff}').unwrap_or(s);
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+
+            let result = config.parse_line(index + 1, line);
+            let Some(warnings) = warnings.as_deref_mut() else {
+                result?;
+                continue;
+            };
+            let (key, _) = parser::split_key_value(line);
+            let lowercase_key = key.to_ascii_lowercase();
+
+            if let Err(error) = result {
+                warnings.push(ParseWarning::InvalidValue {
+                    line: index + 1,
+                    message: error.to_string(),
+                });
+                continue;
+            }
+
+            if !KEYS.contains(&lowercase_key.as_str()) {
+                warnings.push(ParseWarning::UnknownKey {
+                    key: key.to_owned(),
+                    line: index + 1,
+                });
+            }
+
+            if seen.contains(&lowercase_key) {
+                warnings.push(ParseWarning::DuplicateKey {
+                    key: key.to_owned(),
+                    line: index + 1,
+                });
+            } else {
+                seen.push(lowercase_key);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Parse a non-empty, non-comment line into the config.
+    fn parse_line(&mut self, line_number: usize, line: &str) -> Result<(), LibSDBootConfError> {
+        let error = || LibSDBootConfError::ConfigParseErrorAt {
+            line: line_number,
+            content: line.to_owned(),
+        };
+        let (key, value) = parser::split_key_value(line);
+        let lowercase_key = key.to_ascii_lowercase();
+        let value = match (lowercase_key.as_str(), value) {
+            (_, Some(value)) => value,
+            ("editor" | "auto-entries" | "auto-firmware" | "beep", None) => "yes",
+            (_, None) => {
+                return Err(LibSDBootConfError::MissingValue {
+                    key: key.to_owned(),
+                    line: line_number,
+                })
+            }
+        };
+
+        match lowercase_key.as_str() {
+            "default" => self.default = Some(value.to_string()),
+            "timeout" => self.timeout = Some(value.parse().map_err(|_| error())?),
+            "console-mode" => self.console_mode = Some(value.parse().map_err(|_| error())?),
+            "editor" => self.editor = Some(parse_bool(value).map_err(|_| error())?),
+            "auto-entries" => self.auto_entries = Some(parse_bool(value).map_err(|_| error())?),
+            "auto-firmware" => self.auto_firmware = Some(parse_bool(value).map_err(|_| error())?),
+            "beep" => self.beep = Some(parse_bool(value).map_err(|_| error())?),
+            "random-seed-mode" => self.random_seed_mode = Some(value.parse().map_err(|_| error())?),
+            "secure-boot-enroll" => {
+                self.secure_boot_enroll = Some(value.parse().map_err(|_| error())?)
+            }
+            _ => {
+                self.extra.insert(lowercase_key, value.to_owned());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a `ConfigBuilder`, same as `ConfigBuilder::new()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::Config;
+    ///
+    /// let config = Config::builder().timeout(5u32).build();
+    ///
+    /// assert_eq!(config.countdown_seconds(), Some(5));
+    /// ```
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// Read a config from a reader, such as a network stream or a file in an archive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::Config;
+    /// use std::io::Cursor;
+    ///
+    /// let config = Config::from_reader(Cursor::new(b"timeout 5\n")).unwrap();
+    ///
+    /// assert_eq!(config.countdown_seconds(), Some(5));
+    /// ```
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Config, LibSDBootConfError> {
+        let mut content = String::new();
+
+        reader.read_to_string(&mut content)?;
+
+        Config::from_str(&content)
+    }
+
     /// Load an existing config file.
     ///
     /// # Examples
@@ -107,11 +511,36 @@ impl Config {
     /// let config = Config::load("/path/to/config").unwrap();
     /// ```
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, LibSDBootConfError> {
-        Config::from_str(&fs::read_to_string(path.as_ref())?)
+        Config::from_str(&util::read_to_string(path.as_ref())?)
+    }
+
+    /// Load an existing config file like `load`, but collect the problems found as warnings
+    /// instead of failing, see `Config::parse_with_report()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::config::Config;
+    ///
+    /// let report = Config::load_with_report("/path/to/config").unwrap();
+    ///
+    /// for warning in report.warnings {
+    ///     println!("{:?}", warning);
+    /// }
+    /// ```
+    pub fn load_with_report<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<ParseReport<Config>, LibSDBootConfError> {
+        Ok(Config::parse_with_report(&util::read_to_string(
+            path.as_ref(),
+        )?))
     }
 
     /// Save the config to a file.
     ///
+    /// The config is written to a temporary file in the same directory first, which then replaces
+    /// the file, so that an interrupted write never leaves a truncated file behind.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -121,6 +550,49 @@ impl Config {
     /// config.write("/path/to/config").unwrap();
     /// ```
     pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), LibSDBootConfError> {
+        util::write_atomic(path, &self.to_string())?;
+
+        Ok(())
+    }
+
+    /// Save the config to a file like `write`, with a comment header before the first key.
+    ///
+    /// Every line of `header` is prefixed with `# ` unless it already starts with `#`. Comments are
+    /// skipped when loading, so the header is not kept by a subsequent load and write.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::config::Config;
+    ///
+    /// let config = Config::new(Some("5.12.0-aosc-main"), Some(5u32));
+    /// config
+    ///     .write_with_header("/path/to/config", "Generated by aosc-installer, do not edit")
+    ///     .unwrap();
+    /// ```
+    pub fn write_with_header<P: AsRef<Path>>(
+        &self,
+        path: P,
+        header: &str,
+    ) -> Result<(), LibSDBootConfError> {
+        util::write_atomic(path, &(util::comment_header(header) + &self.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Save the config to a file by truncating and writing to it directly.
+    ///
+    /// Unlike `write`, the file may be left truncated if the write is interrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::config::Config;
+    ///
+    /// let config = Config::new(Some("5.12.0-aosc-main"), Some(5u32));
+    /// config.write_non_atomic("/path/to/config").unwrap();
+    /// ```
+    pub fn write_non_atomic<P: AsRef<Path>>(&self, path: P) -> Result<(), LibSDBootConfError> {
         fs::write(path.as_ref(), self.to_string())?;
 
         Ok(())
@@ -128,6 +600,9 @@ impl Config {
 
     /// Set an Entry as the default boot entry.
     ///
+    /// The stored `default` is the filename of the entry, i.e. its id with the `.conf` suffix.
+    /// Setting the same entry again results in the same value.
+    ///
     /// # Examples
     ///
     /// ```
@@ -141,20 +616,273 @@ impl Config {
     /// assert_eq!(config.default, Some("5.12.0-aosc-main.conf".to_owned()));
     /// ```
     pub fn set_default(&mut self, default: &Entry) {
-        self.default = Some(
-            default.id.clone()
-                + default
-                    .id
-                    .ends_with(".conf")
-                    .not()
-                    .then_some(".conf")
-                    .unwrap_or_default(),
-        );
+        self.default = Some(default.filename());
+    }
+
+    /// Unset the default boot entry, letting systemd-boot select one automatically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::Config;
+    ///
+    /// let mut config = Config::new(Some("5.12.0-aosc-main.conf"), Some(5u32));
+    ///
+    /// config.clear_default();
+    ///
+    /// assert_eq!(config.default, None);
+    /// ```
+    pub fn clear_default(&mut self) {
+        self.default = None;
+    }
+
+    /// Unset a key by its name in `loader.conf`, such as `timeout`, or remove it from `extra`.
+    ///
+    /// Keys are matched case-insensitively like when parsing. Returns whether the key was set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::Config;
+    ///
+    /// let mut config = Config::new(Some("5.12.0-aosc-main.conf"), Some(5u32));
+    ///
+    /// assert!(config.unset("timeout"));
+    /// assert!(!config.unset("timeout"));
+    /// assert_eq!(config.timeout, None);
+    /// ```
+    pub fn unset(&mut self, key: &str) -> bool {
+        fn take<T>(field: &mut Option<T>) -> bool {
+            field.take().is_some()
+        }
+
+        match key.to_ascii_lowercase().as_str() {
+            "default" => take(&mut self.default),
+            "timeout" => take(&mut self.timeout),
+            "console-mode" => take(&mut self.console_mode),
+            "editor" => take(&mut self.editor),
+            "auto-entries" => take(&mut self.auto_entries),
+            "auto-firmware" => take(&mut self.auto_firmware),
+            "beep" => take(&mut self.beep),
+            "random-seed-mode" => take(&mut self.random_seed_mode),
+            "secure-boot-enroll" => take(&mut self.secure_boot_enroll),
+            lowercase_key => self.extra.remove(lowercase_key).is_some(),
+        }
+    }
+
+    /// The number of seconds a countdown before booting the default entry should display.
+    ///
+    /// - `Timeout::Seconds(n)` maps to `Some(n)`.
+    /// - `Timeout::MenuForce` maps to `Some(0)`, as the menu is shown without a countdown until
+    ///   an entry is selected.
+    /// - `Timeout::MenuHidden` and `Timeout::MenuDisabled` map to `None`, as no menu is shown.
+    /// - An unset timeout maps to `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::config::{Config, Timeout};
+    ///
+    /// let config = Config::new(None::<String>, Some(Timeout::MenuForce));
+    ///
+    /// assert_eq!(config.countdown_seconds(), Some(0));
+    /// ```
+    pub fn countdown_seconds(&self) -> Option<u32> {
+        match self.timeout? {
+            Timeout::Seconds(seconds) => Some(seconds),
+            Timeout::MenuForce => Some(0),
+            Timeout::MenuHidden | Timeout::MenuDisabled => None,
+        }
+    }
+
+    /// Whether the default entry is booted without showing the menu.
+    ///
+    /// Returns `true` for `Timeout::Seconds(0)`, `Timeout::MenuHidden` and
+    /// `Timeout::MenuDisabled`, and `false` for other timeouts or an unset timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::config::{Config, Timeout};
+    ///
+    /// assert!(Config::new(None::<String>, Some(0u32)).boots_immediately());
+    /// assert!(!Config::new(None::<String>, None::<Timeout>).boots_immediately());
+    /// ```
+    pub fn boots_immediately(&self) -> bool {
+        matches!(
+            self.timeout,
+            Some(Timeout::Seconds(0) | Timeout::MenuHidden | Timeout::MenuDisabled)
+        )
+    }
+
+    /// Overlay another configuration onto this one.
+    ///
+    /// Fields set in `other` replace the ones in `self`, and fields unset in `other` are left
+    /// untouched. Keys in `extra` are merged, with `other` winning on collisions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::config::{Config, Timeout};
+    ///
+    /// let mut config = Config::new(Some("5.12.0-aosc-main.conf"), Some(5u32));
+    ///
+    /// config.merge(Config::new(None::<String>, Some(Timeout::MenuForce)));
+    ///
+    /// assert_eq!(config.default, Some("5.12.0-aosc-main.conf".to_owned()));
+    /// assert_eq!(config.timeout, Some(Timeout::MenuForce));
+    /// ```
+    pub fn merge(&mut self, other: Config) {
+        if other.default.is_some() {
+            self.default = other.default;
+        }
+
+        if other.timeout.is_some() {
+            self.timeout = other.timeout;
+        }
+
+        if other.console_mode.is_some() {
+            self.console_mode = other.console_mode;
+        }
+
+        if other.editor.is_some() {
+            self.editor = other.editor;
+        }
+
+        if other.auto_entries.is_some() {
+            self.auto_entries = other.auto_entries;
+        }
+
+        if other.auto_firmware.is_some() {
+            self.auto_firmware = other.auto_firmware;
+        }
+
+        if other.beep.is_some() {
+            self.beep = other.beep;
+        }
+
+        if other.random_seed_mode.is_some() {
+            self.random_seed_mode = other.random_seed_mode;
+        }
+
+        if other.secure_boot_enroll.is_some() {
+            self.secure_boot_enroll = other.secure_boot_enroll;
+        }
+
+        self.extra.extend(other.extra);
+    }
+
+    /// Get the id of the entry the `default` field points at, i.e. its value without a trailing
+    /// `.conf`.
+    ///
+    /// No file is read, use `default_entry` to load the entry. Returns `None` if the config does
+    /// not contain a `default` field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::Config;
+    ///
+    /// let config = Config::new(Some("5.12.0-aosc-main.conf"), Some(5u32));
+    ///
+    /// assert_eq!(config.default_entry_id(), Some("5.12.0-aosc-main"));
+    /// ```
+    pub fn default_entry_id(&self) -> Option<&str> {
+        self.default
+            .as_deref()
+            .map(|default| default.strip_suffix(".conf").unwrap_or(default))
+    }
+
+    /// Whether the `default` field selects the given entry.
+    ///
+    /// `default` is matched as a glob pattern, where `*` matches any sequence of characters and
+    /// `?` matches a single character, against the filename of the entry both with and without
+    /// the `.conf` suffix. Returns `false` if the config does not contain a `default` field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Config, Entry};
+    ///
+    /// let config = Config::new(Some("linux-*"), None::<u32>);
+    ///
+    /// assert!(config.matches_default(&Entry::new("linux-5.12", Vec::new())));
+    /// assert!(!config.matches_default(&Entry::new("windows", Vec::new())));
+    /// ```
+    pub fn matches_default(&self, entry: &Entry) -> bool {
+        let filename = entry.filename();
+        let id = filename.strip_suffix(".conf").unwrap_or(&filename);
+
+        self.default.as_deref().is_some_and(|default| {
+            util::glob_match(default, &filename) || util::glob_match(default, id)
+        })
+    }
+
+    /// Update the text of an existing `loader.conf` with the keys set in this config, leaving
+    /// everything else untouched.
+    ///
+    /// The first line of every key set in this config is replaced in place, and its other lines
+    /// are removed. Keys missing from `existing` are appended at the end. Comments, blank lines,
+    /// and keys unset in this config are kept as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::Config;
+    ///
+    /// let config = Config::new(None::<String>, Some(3u32));
+    ///
+    /// assert_eq!(
+    ///     config.apply_to_existing("# menu\ntimeout 5\ndefault aosc.conf\n"),
+    ///     "# menu\ntimeout 3\ndefault aosc.conf\n"
+    /// );
+    /// ```
+    pub fn apply_to_existing(&self, existing: &str) -> String {
+        let serialized = self.to_string();
+        let mut lines = serialized
+            .lines()
+            .map(|line| {
+                let (key, _) = parser::split_key_value(line);
+
+                (key.to_ascii_lowercase(), Some(line))
+            })
+            .collect::<Vec<_>>();
+        let mut result = String::new();
+
+        for line in existing.lines() {
+            let trimmed = line.trim();
+            let (key, _) = parser::split_key_value(trimmed);
+            let key = key.to_ascii_lowercase();
+
+            match lines.iter_mut().find(|(other, _)| *other == key) {
+                Some((_, replacement)) if !trimmed.starts_with('#') && !trimmed.is_empty() => {
+                    if let Some(replacement) = replacement.take() {
+                        result.push_str(replacement);
+                        result.push('\n');
+                    }
+                }
+                _ => {
+                    result.push_str(line);
+                    result.push('\n');
+                }
+            }
+        }
+
+        for (_, line) in lines {
+            if let Some(line) = line {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+
+        result
     }
 
     /// Try to load the default entry as an Entry object.
     ///
-    /// Returns `None` if the config does not contain a `default` field.
+    /// Returns `None` if the config does not contain a `default` field. Like systemd-boot, the
+    /// `default` value does not need to include the `.conf` suffix: if no file named exactly after
+    /// the value exists in the directory, `<value>.conf` is loaded instead.
     ///
     /// # Examples
     ///
@@ -171,7 +899,15 @@ impl Config {
     ) -> Result<Option<Entry>, LibSDBootConfError> {
         self.default
             .as_ref()
-            .map(|default| Entry::load(directory.as_ref().join(default)))
+            .map(|default| {
+                let path = directory.as_ref().join(default);
+
+                if path.is_file() || default.ends_with(".conf") {
+                    Entry::load(path)
+                } else {
+                    Entry::load(directory.as_ref().join(format!("{}.conf", default)))
+                }
+            })
             .transpose()
     }
 }
@@ -196,8 +932,56 @@ impl ConfigBuilder {
     );
     generate_builder_method!(
         /// Set the timeout.
-        option INNER(inner) timeout(U: u32)
+        option INNER(inner) timeout(U: Timeout)
     );
+    generate_builder_method!(
+        /// Set the console mode.
+        option INNER(inner) console_mode(C: ConsoleMode)
+    );
+    generate_builder_method!(
+        /// Enable or disable the kernel command line editor.
+        option INNER(inner) editor(B: bool)
+    );
+    generate_builder_method!(
+        /// Show or hide entries for other boot loaders found on the ESP.
+        option INNER(inner) auto_entries(B: bool)
+    );
+    generate_builder_method!(
+        /// Show or hide the entry for rebooting into the firmware setup.
+        option INNER(inner) auto_firmware(B: bool)
+    );
+    generate_builder_method!(
+        /// Enable or disable beeping when the menu is shown.
+        option INNER(inner) beep(B: bool)
+    );
+    generate_builder_method!(
+        /// Set how the random seed file is used.
+        option INNER(inner) random_seed_mode(R: RandomSeedMode)
+    );
+    generate_builder_method!(
+        /// Set whether Secure Boot keys are enrolled.
+        option INNER(inner) secure_boot_enroll(S: SecureBootEnroll)
+    );
+
+    /// Set the timeout by parsing a string, such as a command line argument.
+    ///
+    /// Accepts a number of seconds, `menu-force`, `menu-hidden`, or `menu-disabled`, and returns
+    /// `LibSDBootConfError::ConfigParseError` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::config::{ConfigBuilder, Timeout};
+    ///
+    /// let config = ConfigBuilder::new().timeout_str("menu-disabled").unwrap().build();
+    ///
+    /// assert_eq!(config.timeout, Some(Timeout::MenuDisabled));
+    /// ```
+    pub fn timeout_str(mut self, timeout: &str) -> Result<Self, LibSDBootConfError> {
+        self.inner.timeout = Some(timeout.parse()?);
+
+        Ok(self)
+    }
 
     /// Set the default entry with an `Entry`.
     pub fn default_entry(mut self, entry: &Entry) -> Self {
@@ -211,3 +995,549 @@ impl ConfigBuilder {
         self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_default_entry_without_suffix() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("linux-6.1.conf"), "title Linux 6.1\n").unwrap();
+
+        for default in ["linux-6.1", "linux-6.1.conf"] {
+            let config = Config::new(Some(default), None::<u32>);
+            let entry = config.default_entry(dir.path()).unwrap().unwrap();
+
+            assert_eq!(entry.id, "linux-6.1");
+        }
+    }
+
+    #[test]
+    fn test_countdown_seconds() {
+        let countdown =
+            |timeout: Option<Timeout>| Config::new(None::<String>, timeout).countdown_seconds();
+
+        assert_eq!(countdown(Some(Timeout::Seconds(5))), Some(5));
+        assert_eq!(countdown(Some(Timeout::MenuForce)), Some(0));
+        assert_eq!(countdown(Some(Timeout::MenuHidden)), None);
+        assert_eq!(countdown(Some(Timeout::MenuDisabled)), None);
+        assert_eq!(countdown(None), None);
+    }
+
+    #[test]
+    fn test_timeout_zero_unset_hidden() {
+        let zero = Config::from_str("timeout 0\n").unwrap();
+        let unset = Config::from_str("").unwrap();
+        let hidden = Config::from_str("timeout menu-hidden\n").unwrap();
+
+        assert_eq!(zero.timeout, Some(Timeout::Seconds(0)));
+        assert_eq!(unset.timeout, None);
+        assert_eq!(hidden.timeout, Some(Timeout::MenuHidden));
+        assert!(zero.boots_immediately());
+        assert!(!unset.boots_immediately());
+        assert!(hidden.boots_immediately());
+        assert_eq!(zero.countdown_seconds(), Some(0));
+        assert_eq!(hidden.countdown_seconds(), None);
+    }
+
+    #[test]
+    fn test_timeout_none_and_zero_to_string() {
+        let unset = Config {
+            timeout: None,
+            ..Default::default()
+        };
+        let zero = Config {
+            timeout: Some(Timeout::Seconds(0)),
+            ..Default::default()
+        };
+
+        assert!(!unset.to_string().contains("timeout"));
+        assert_eq!(unset.to_string(), "");
+        assert_eq!(zero.to_string(), "timeout 0\n");
+        assert_eq!(Config::from_str(&zero.to_string()).unwrap(), zero);
+        assert_eq!(Config::from_str(&unset.to_string()).unwrap(), unset);
+    }
+
+    #[test]
+    fn test_console_mode() {
+        for (value, console_mode) in [
+            ("auto", ConsoleMode::Auto),
+            ("max", ConsoleMode::Max),
+            ("keep", ConsoleMode::Keep),
+            ("2", ConsoleMode::Mode(2)),
+        ] {
+            let content = format!("console-mode {}\n", value);
+            let config = Config::from_str(&content).unwrap();
+
+            assert_eq!(config.console_mode, Some(console_mode));
+            assert_eq!(config.to_string(), content);
+        }
+
+        assert_eq!(
+            ConfigBuilder::new()
+                .console_mode(ConsoleMode::Mode(2))
+                .build()
+                .to_string(),
+            "console-mode 2\n"
+        );
+        assert!(matches!(
+            Config::from_str("console-mode huge\n"),
+            Err(LibSDBootConfError::ConfigParseErrorAt { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_editor() {
+        for (value, editor) in [("yes", true), ("1", true), ("no", false), ("0", false)] {
+            let config = Config::from_str(&format!("editor {}\n", value)).unwrap();
+
+            assert_eq!(config.editor, Some(editor));
+        }
+
+        assert_eq!(
+            ConfigBuilder::new().editor(false).build().to_string(),
+            "editor no\n"
+        );
+        assert_eq!(
+            Config::from_str("editor 1\n").unwrap().to_string(),
+            "editor yes\n"
+        );
+        assert!(matches!(
+            Config::from_str("editor maybe\n"),
+            Err(LibSDBootConfError::ConfigParseErrorAt { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_line() {
+        let error = Config::from_str("# loader.conf\ndefault foo\n\neditor maybe\n").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid configuration at line 4: editor maybe"
+        );
+    }
+
+    #[test]
+    fn test_set_default_idempotent() {
+        let mut config = Config::default();
+
+        for id in ["5.12.0-aosc-main", "5.12.0-aosc-main.conf"] {
+            let entry = Entry::new(id, Vec::new());
+
+            config.set_default(&entry);
+            config.set_default(&entry);
+
+            assert_eq!(config.default, Some("5.12.0-aosc-main.conf".to_owned()));
+        }
+
+        config.clear_default();
+
+        assert_eq!(config.default, None);
+        assert_eq!(config.to_string(), "");
+    }
+
+    #[test]
+    fn test_tab_separated() {
+        assert_eq!(
+            Config::from_str("default\t5.12.0-aosc-main.conf\ntimeout\t5\n").unwrap(),
+            Config::from_str("default 5.12.0-aosc-main.conf\ntimeout 5\n").unwrap()
+        );
+        assert_eq!(
+            Config::from_str("timeout\t5\n").unwrap().timeout,
+            Some(Timeout::Seconds(5))
+        );
+    }
+
+    #[test]
+    fn test_extra() {
+        let config = Config::from_str(
+            "timeout 5\n\
+             x-aosc-theme dark\n\
+             default 5.12.0-aosc-main.conf\n\
+             reboot-for-bitlocker yes\n\
+             x-aosc-flavor main\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.extra.len(), 3);
+        assert_eq!(
+            config.to_string(),
+            "default 5.12.0-aosc-main.conf\n\
+             timeout 5\n\
+             reboot-for-bitlocker yes\n\
+             x-aosc-flavor main\n\
+             x-aosc-theme dark\n"
+        );
+    }
+
+    #[test]
+    fn test_crlf() {
+        let config = Config::from_str("default aosc.conf\r\ntimeout 5\r\n\r\n").unwrap();
+
+        assert_eq!(config.default, Some("aosc.conf".to_owned()));
+        assert_eq!(config.timeout, Some(Timeout::Seconds(5)));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut config = Config::from_str(
+            "default aosc.conf\n\
+             timeout 5\n\
+             editor no\n\
+             x-aosc-flavor main\n\
+             x-aosc-theme dark\n",
+        )
+        .unwrap();
+
+        config.merge(
+            Config::from_str(
+                "timeout menu-force\n\
+                 console-mode max\n\
+                 x-aosc-flavor desktop\n",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(config.default, Some("aosc.conf".to_owned()));
+        assert_eq!(config.timeout, Some(Timeout::MenuForce));
+        assert_eq!(config.console_mode, Some(ConsoleMode::Max));
+        assert_eq!(config.editor, Some(false));
+        assert_eq!(config.extra["x-aosc-flavor"], "desktop");
+        assert_eq!(config.extra["x-aosc-theme"], "dark");
+    }
+
+    #[test]
+    fn test_merge_empty() {
+        let config = Config::new(Some("aosc.conf"), Some(5u32));
+        let mut merged = Config::new(Some("aosc.conf"), Some(5u32));
+
+        merged.merge(Config::default());
+
+        assert_eq!(merged, config);
+    }
+
+    #[test]
+    fn test_trailing_newline() {
+        let content = Config::new(Some("aosc.conf"), Some(5u32)).to_string();
+
+        assert_eq!(Config::default().to_string(), "");
+        assert!(content.ends_with('\n'));
+        assert!(!content.contains("\n\n"));
+    }
+
+    #[test]
+    fn test_timeout_parse() {
+        assert_eq!(
+            Config::from_str("timeout 0\n").unwrap().timeout,
+            Some(Timeout::Seconds(0))
+        );
+        assert_eq!(
+            Config::from_str("timeout menu-disabled\n").unwrap().timeout,
+            Some(Timeout::MenuDisabled)
+        );
+
+        let error = Config::from_str("default aosc.conf\ntimeout soon\n").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid configuration at line 2: timeout soon"
+        );
+        assert!(Config::from_str("timeout -1\n").is_err());
+    }
+
+    #[test]
+    fn test_case_insensitive_keys() {
+        let config = Config::from_str("Default AOSC.conf\nTIMEOUT 3\nX-Custom Value\n").unwrap();
+
+        assert_eq!(config.default, Some("AOSC.conf".to_owned()));
+        assert_eq!(config.timeout, Some(Timeout::Seconds(3)));
+        assert_eq!(
+            config.to_string(),
+            "default AOSC.conf\ntimeout 3\nx-custom Value\n"
+        );
+
+        let config = Config::from_str("X-Foo a\nx-foo b\n").unwrap();
+
+        assert_eq!(config.extra.len(), 1);
+        assert_eq!(config.extra["x-foo"], "b");
+        assert_eq!(config.to_string(), "x-foo b\n");
+    }
+
+    #[test]
+    fn test_boolean_keys_and_random_seed_mode() {
+        let content = "editor no\n\
+                       auto-entries yes\n\
+                       auto-firmware no\n\
+                       beep yes\n\
+                       random-seed-mode with-system-token\n";
+        let config = Config::from_str(content).unwrap();
+
+        assert_eq!(config.auto_entries, Some(true));
+        assert_eq!(config.auto_firmware, Some(false));
+        assert_eq!(config.beep, Some(true));
+        assert_eq!(
+            config.random_seed_mode,
+            Some(RandomSeedMode::WithSystemToken)
+        );
+        assert!(config.extra.is_empty());
+        assert_eq!(config.to_string(), content);
+        assert_eq!(
+            ConfigBuilder::new()
+                .editor(false)
+                .auto_entries(true)
+                .auto_firmware(false)
+                .beep(true)
+                .random_seed_mode(RandomSeedMode::WithSystemToken)
+                .build(),
+            config
+        );
+        assert!(Config::from_str("random-seed-mode sometimes\n").is_err());
+    }
+
+    #[test]
+    fn test_key_only_lines() {
+        let config = Config::from_str("editor\nbeep\n").unwrap();
+
+        assert_eq!(config.editor, Some(true));
+        assert_eq!(config.beep, Some(true));
+        assert_eq!(
+            Config::from_str("timeout 5\ndefault\n")
+                .unwrap_err()
+                .to_string(),
+            "missing value for 'default' at line 2"
+        );
+    }
+
+    #[test]
+    fn test_secure_boot_enroll() {
+        for (value, secure_boot_enroll) in [
+            ("off", SecureBootEnroll::Off),
+            ("manual", SecureBootEnroll::Manual),
+            ("if-safe", SecureBootEnroll::IfSafe),
+            ("force", SecureBootEnroll::Force),
+        ] {
+            let content = format!("secure-boot-enroll {}\n", value);
+            let config = Config::from_str(&content).unwrap();
+
+            assert_eq!(config.secure_boot_enroll, Some(secure_boot_enroll));
+            assert_eq!(config.to_string(), content);
+            assert_eq!(
+                ConfigBuilder::new()
+                    .secure_boot_enroll(secure_boot_enroll)
+                    .build(),
+                config
+            );
+        }
+
+        assert_eq!(
+            Config::from_str("secure-boot-enroll always\n")
+                .unwrap_err()
+                .to_string(),
+            "invalid configuration at line 1: secure-boot-enroll always"
+        );
+    }
+
+    #[test]
+    fn test_default_entry_id() {
+        let suffixed = Config::new(Some("5.12.0-aosc-main.conf"), None::<u32>);
+        let bare = Config::new(Some("5.12.0-aosc-main"), None::<u32>);
+
+        assert_eq!(suffixed.default_entry_id(), Some("5.12.0-aosc-main"));
+        assert_eq!(bare.default_entry_id(), Some("5.12.0-aosc-main"));
+        assert_eq!(Config::default().default_entry_id(), None);
+    }
+
+    #[test]
+    fn test_write_with_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loader.conf");
+        let config = Config::new(Some("aosc.conf"), Some(5u32));
+
+        config
+            .write_with_header(&path, "Generated by aosc-installer, do not edit")
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Generated by aosc-installer, do not edit\n\
+             default aosc.conf\n\
+             timeout 5\n"
+        );
+        assert_eq!(Config::load(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn test_timeout_str() {
+        let timeout = |value| {
+            ConfigBuilder::new()
+                .timeout_str(value)
+                .map(|builder| builder.build().timeout)
+        };
+
+        assert_eq!(timeout("5").unwrap(), Some(Timeout::Seconds(5)));
+        assert_eq!(
+            timeout("menu-disabled").unwrap(),
+            Some(Timeout::MenuDisabled)
+        );
+        assert!(matches!(
+            timeout("abc"),
+            Err(LibSDBootConfError::ConfigParseError)
+        ));
+    }
+
+    #[test]
+    fn test_apply_to_existing() {
+        let existing = "# Managed by hand, keep the console mode\n\
+                        \n\
+                        console-mode keep\n\
+                        Timeout 5\n\
+                        x-aosc-flavor main\n\
+                        timeout 10\n";
+        let config = ConfigBuilder::new()
+            .default("aosc.conf")
+            .timeout(3u32)
+            .build();
+
+        assert_eq!(
+            config.apply_to_existing(existing),
+            "# Managed by hand, keep the console mode\n\
+             \n\
+             console-mode keep\n\
+             timeout 3\n\
+             x-aosc-flavor main\n\
+             default aosc.conf\n"
+        );
+        assert_eq!(Config::default().apply_to_existing(existing), existing);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let config =
+            Config::from_reader(io::Cursor::new(b"default aosc.conf\ntimeout 5\n".to_vec()))
+                .unwrap();
+
+        assert_eq!(config, Config::new(Some("aosc.conf"), Some(5u32)));
+        assert!(matches!(
+            Config::from_reader(io::Cursor::new(vec![0xff, 0xfe])),
+            Err(LibSDBootConfError::IOError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_with_report() {
+        let report = Config::parse_with_report(
+            "timeout 5\n\
+             x-aosc-flavor main\n\
+             editor maybe\n\
+             Timeout 3\n",
+        );
+
+        assert_eq!(report.value.timeout, Some(Timeout::Seconds(3)));
+        assert_eq!(report.value.editor, None);
+        assert_eq!(
+            report.warnings,
+            vec![
+                ParseWarning::UnknownKey {
+                    key: "x-aosc-flavor".to_owned(),
+                    line: 2,
+                },
+                ParseWarning::InvalidValue {
+                    line: 3,
+                    message: "invalid configuration at line 3: editor maybe".to_owned(),
+                },
+                ParseWarning::DuplicateKey {
+                    key: "Timeout".to_owned(),
+                    line: 4,
+                },
+            ]
+        );
+
+        let report = Config::parse_with_report("editor maybe\neditor no\neditor yes\n");
+
+        assert_eq!(report.value.editor, Some(true));
+        assert_eq!(
+            report.warnings,
+            vec![
+                ParseWarning::InvalidValue {
+                    line: 1,
+                    message: "invalid configuration at line 1: editor maybe".to_owned(),
+                },
+                ParseWarning::DuplicateKey {
+                    key: "editor".to_owned(),
+                    line: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_default() {
+        let entry = Entry::new("linux-5.12", Vec::new());
+
+        for default in [
+            "linux-*",
+            "linux-5.12",
+            "linux-5.12.conf",
+            "linux-?.??.conf",
+        ] {
+            assert!(Config::new(Some(default), None::<u32>).matches_default(&entry));
+        }
+
+        assert!(!Config::new(Some("linux-6.*"), None::<u32>).matches_default(&entry));
+        assert!(!Config::default().matches_default(&entry));
+    }
+
+    #[test]
+    fn test_leading_whitespace() {
+        let config =
+            Config::from_str("  # indented comment\n\tdefault aosc.conf\n    timeout 5\n").unwrap();
+
+        assert_eq!(config, Config::new(Some("aosc.conf"), Some(5u32)));
+        assert!(config.extra.is_empty());
+    }
+
+    #[test]
+    fn test_unset() {
+        let mut config =
+            Config::from_str("default aosc.conf\nbeep yes\nx-aosc-flavor main\n").unwrap();
+
+        assert!(config.unset("Beep"));
+        assert!(config.unset("x-aosc-flavor"));
+        assert!(!config.unset("x-aosc-flavor"));
+        assert!(!config.unset("timeout"));
+        assert_eq!(config.to_string(), "default aosc.conf\n");
+    }
+
+    #[test]
+    fn test_bom() {
+        let config = Config::from_str("\u{feff}default aosc.conf\ntimeout 5\n").unwrap();
+
+        assert_eq!(config.default, Some("aosc.conf".to_owned()));
+        assert!(config.extra.is_empty());
+    }
+
+    #[test]
+    fn test_parse_named() {
+        let path = Path::new("/etc/aosc/loader.conf.d/10-timeout.conf");
+
+        assert_eq!(
+            Config::parse_named("timeout 5\n", path).unwrap(),
+            Config::from_str("timeout 5\n").unwrap()
+        );
+
+        let error = Config::parse_named("default a\ntimeout\n", path).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "failed to parse /etc/aosc/loader.conf.d/10-timeout.conf: \
+             missing value for 'timeout' at line 2"
+        );
+        assert!(matches!(
+            error,
+            LibSDBootConfError::ParseErrorIn { path: error_path, .. } if error_path == path
+        ));
+    }
+}
@@ -0,0 +1,52 @@
+//! Differences between two systemd-boot configurations.
+//!
+//! Create a `ConfDiff` with `SystemdBootConf::diff()`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsdbootconf::SystemdBootConf;
+//!
+//! let current = SystemdBootConf::load("/efi/loader").unwrap();
+//! let mut planned = SystemdBootConf::load("/efi/loader").unwrap();
+//!
+//! planned.remove_entry("5.12.0-aosc-main");
+//!
+//! println!("{:#?}", current.diff(&planned));
+//! ```
+
+/// A change of a key of the loader configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConfigChange {
+    /// The key as written in `loader.conf`, such as `timeout`.
+    pub key: String,
+    /// The value before the change, `None` if the key was unset.
+    pub old: Option<String>,
+    /// The value after the change, `None` if the key is unset.
+    pub new: Option<String>,
+}
+
+/// The differences between two systemd-boot configurations.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConfDiff {
+    /// Changed keys of the loader configuration, sorted by key.
+    pub config_changes: Vec<ConfigChange>,
+    /// Ids of the entries only present in the new configuration.
+    pub added_entries: Vec<String>,
+    /// Ids of the entries only present in the old configuration.
+    pub removed_entries: Vec<String>,
+    /// Ids of the entries present in both configurations with different tokens.
+    pub modified_entries: Vec<String>,
+}
+
+impl ConfDiff {
+    /// Whether the two configurations are the same.
+    pub fn is_empty(&self) -> bool {
+        self.config_changes.is_empty()
+            && self.added_entries.is_empty()
+            && self.removed_entries.is_empty()
+            && self.modified_entries.is_empty()
+    }
+}
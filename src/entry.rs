@@ -6,6 +6,10 @@
 //! Tokens are the possible fields of an `Entry`, visit
 //! <https://www.freedesktop.org/wiki/Software/systemd/systemd-boot/> for more information.
 //!
+//! When parsing, an `options` line ending with `\` is joined with the following line, as some
+//! editors wrap long kernel command lines this way. Entries are always written with one line per
+//! token.
+//!
 //! # Examples
 //!
 //! ```
@@ -22,15 +26,28 @@
 //! assert_eq!(entry.to_string(), built.to_string());
 
 use std::{
-    fs,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    fmt, fs,
+    hash::{Hash, Hasher},
+    io::Read,
+    mem,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use crate::{generate_builder_method, LibSDBootConfError};
+use crate::{
+    generate_builder_method, generate_token_method, parser,
+    report::{EntryLint, ParseReport, ParseWarning},
+    util, LibSDBootConfError,
+};
 
 /// Possible fields of an `Entry`.
-#[derive(Debug, PartialEq)]
+///
+/// New variants may be added in future releases as systemd-boot gains new keys, so matches on
+/// this enum need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Token {
     /// Text to show in the menu.
     Title(String),
@@ -46,17 +63,31 @@ pub enum Token {
     Linux(PathBuf),
     /// Initramfs image (systemd-boot just adds this as option initrd=)
     Initrd(PathBuf),
+    /// Devicetree binary to load before booting
+    DeviceTree(PathBuf),
+    /// Devicetree overlays to apply on top of the devicetree
+    DeviceTreeOverlay(Vec<PathBuf>),
+    /// EFI architecture the entry is meant for, such as `x64` or `aa64`
+    Architecture(String),
+    /// Key to sort the entry by in the menu
+    SortKey(String),
+    /// A key not modeled by this crate, preserved as-is apart from the key being lowercased
+    Unknown {
+        /// The key of the line, lowercased like the known keys.
+        key: String,
+        /// The value of the line.
+        value: String,
+    },
 }
 
 impl FromStr for Token {
     type Err = LibSDBootConfError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.splitn(2, ' ');
-        let key = parts.next().ok_or(LibSDBootConfError::EntryParseError)?;
-        let value = parts.next().ok_or(LibSDBootConfError::EntryParseError)?;
+        let (key, value) = parser::split_key_value(s);
+        let value = value.ok_or(LibSDBootConfError::EntryParseError)?;
 
-        Ok(match key {
+        Ok(match key.to_ascii_lowercase().as_str() {
             "title" => Self::Title(value.to_owned()),
             "version" => Self::Version(value.to_owned()),
             "machine-id" => Self::MachineID(value.to_owned()),
@@ -64,27 +95,179 @@ impl FromStr for Token {
             "options" => Self::Options(value.to_owned()),
             "linux" => Self::Linux(PathBuf::from(value)),
             "initrd" => Self::Initrd(PathBuf::from(value)),
-            _ => return Err(LibSDBootConfError::InvalidToken(key.to_owned())),
+            "devicetree" => Self::DeviceTree(PathBuf::from(value)),
+            "architecture" => Self::Architecture(value.to_owned()),
+            "sort-key" => Self::SortKey(value.to_owned()),
+            "devicetree-overlay" => {
+                Self::DeviceTreeOverlay(value.split_whitespace().map(PathBuf::from).collect())
+            }
+            lowercase_key => Self::Unknown {
+                key: lowercase_key.to_owned(),
+                value: value.to_owned(),
+            },
         })
     }
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
+impl Token {
+    /// The key of the token as written in an entry file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::Token;
+    ///
+    /// assert_eq!(Token::MachineID("0123".to_owned()).key(), "machine-id");
+    /// ```
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Title(_) => "title",
+            Self::Version(_) => "version",
+            Self::MachineID(_) => "machine-id",
+            Self::Efi(_) => "efi",
+            Self::Options(_) => "options",
+            Self::Linux(_) => "linux",
+            Self::Initrd(_) => "initrd",
+            Self::DeviceTree(_) => "devicetree",
+            Self::DeviceTreeOverlay(_) => "devicetree-overlay",
+            Self::Architecture(_) => "architecture",
+            Self::SortKey(_) => "sort-key",
+            Self::Unknown { key, .. } => key,
+        }
+    }
+
+    /// The value of the token as written after the key in an entry file.
+    ///
+    /// The paths of a `DeviceTreeOverlay` are joined with spaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::Token;
+    /// use std::path::PathBuf;
+    ///
+    /// let token = Token::DeviceTreeOverlay(vec![PathBuf::from("/a.dtbo"), PathBuf::from("/b.dtbo")]);
+    ///
+    /// assert_eq!(token.value_string(), "/a.dtbo /b.dtbo");
+    /// ```
+    pub fn value_string(&self) -> String {
+        match self {
+            Self::Title(value)
+            | Self::Version(value)
+            | Self::MachineID(value)
+            | Self::Options(value)
+            | Self::Architecture(value)
+            | Self::SortKey(value)
+            | Self::Unknown { value, .. } => value.clone(),
+            Self::Efi(path) | Self::Linux(path) | Self::Initrd(path) | Self::DeviceTree(path) => {
+                path.display().to_string()
+            }
+            Self::DeviceTreeOverlay(overlays) => overlays
+                .iter()
+                .map(|overlay| overlay.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Whether the token may appear more than once in an entry.
+    ///
+    /// `Initrd`, `DeviceTreeOverlay`, and `Options` are repeatable, systemd-boot uses all of
+    /// their occurrences. For the other tokens only one occurrence is used. `Unknown` tokens are
+    /// considered repeatable as nothing is known about them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::Token;
+    /// use std::path::PathBuf;
+    ///
+    /// assert!(Token::Initrd(PathBuf::from("/initramfs.img")).is_repeatable());
+    /// assert!(!Token::Title("AOSC OS".to_owned()).is_repeatable());
+    /// ```
+    pub fn is_repeatable(&self) -> bool {
+        matches!(
+            self,
+            Self::Initrd(_) | Self::DeviceTreeOverlay(_) | Self::Options(_) | Self::Unknown { .. }
+        )
+    }
+
+    /// Position of the token in the canonical order used by `Entry::normalize_token_order()`.
+    fn canonical_position(&self) -> usize {
         match self {
-            Self::Title(title) => format!("title {}\n", title),
-            Self::Version(version) => format!("version {}\n", version),
-            Self::MachineID(machine_id) => format!("machine-id {}\n", machine_id),
-            Self::Efi(efi) => format!("efi {}\n", efi.display()),
-            Self::Options(options) => format!("options {}\n", options),
-            Self::Linux(linux) => format!("linux {}\n", linux.display()),
-            Self::Initrd(initrd) => format!("initrd {}\n", initrd.display()),
+            Self::Title(_) => 0,
+            Self::Version(_) => 1,
+            Self::MachineID(_) => 2,
+            Self::SortKey(_) => 3,
+            Self::Architecture(_) => 4,
+            Self::Options(_) => 5,
+            Self::Linux(_) => 6,
+            Self::Initrd(_) => 7,
+            Self::Efi(_) => 8,
+            Self::DeviceTree(_) => 9,
+            Self::DeviceTreeOverlay(_) => 10,
+            Self::Unknown { .. } => 11,
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.value_string();
+
+        if value.is_empty() {
+            writeln!(f, "{}", self.key())
+        } else {
+            writeln!(f, "{} {}", self.key(), value)
+        }
+    }
+}
+
+/// The kind of an `Entry`, see `Entry::kind()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    /// The entry boots a Linux kernel with a `linux` token.
+    Linux,
+    /// The entry runs an EFI image, such as a unified kernel image, with an `efi` token.
+    Efi,
+    /// The entry has neither a `linux` nor an `efi` token.
+    Unknown,
+}
+
+/// Which occurrence of a duplicated kernel parameter to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precedence {
+    /// Keep the first occurrence.
+    First,
+    /// Keep the last occurrence.
+    Last,
+}
+
+/// Policy of `Entry::dedup_options_with()`.
+///
+/// Parameters in the form of `key=value` are duplicates when they share the same key, flags are
+/// duplicates when they are identical. The default policy keeps the last `key=value` parameter,
+/// which is the one the kernel uses, and the first flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DedupPolicy {
+    /// Which occurrence of `key=value` parameters to keep.
+    pub key_value: Precedence,
+    /// Which occurrence of flags to keep.
+    pub flags: Precedence,
+}
+
+impl Default for DedupPolicy {
+    fn default() -> Self {
+        Self {
+            key_value: Precedence::Last,
+            flags: Precedence::First,
         }
     }
 }
 
 /// A boot menu entry.
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
     /// The ID of the `Entry`, used in the filename of the entry and the `default` field in a
     /// `Config`.
@@ -97,30 +280,36 @@ impl FromStr for Entry {
     type Err = LibSDBootConfError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut entry = Entry::default();
-        let lines = s.lines();
-
-        for line in lines {
-            if line.starts_with('#') || line.is_empty() {
-                continue;
-            }
-
-            entry.tokens.push(line.parse()?);
-        }
-
-        Ok(entry)
+        Self::parse(s, false, None)
     }
 }
 
-impl ToString for Entry {
-    fn to_string(&self) -> String {
-        let mut s = String::new();
+/// Collect tokens into an `Entry` with an empty id.
+///
+/// # Examples
+///
+/// ```
+/// use libsdbootconf::entry::{Entry, Token};
+///
+/// let mut entry = [Token::Title("AOSC OS".to_owned())]
+///     .into_iter()
+///     .collect::<Entry>();
+///
+/// entry.id = "5.12.0-aosc-main".to_owned();
+/// ```
+impl FromIterator<Token> for Entry {
+    fn from_iter<T: IntoIterator<Item = Token>>(tokens: T) -> Self {
+        Entry::new(String::new(), tokens)
+    }
+}
 
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for token in &self.tokens {
-            s.push_str(&token.to_string())
+            write!(f, "{}", token)?;
         }
 
-        s
+        Ok(())
     }
 }
 
@@ -152,111 +341,2688 @@ impl Entry {
         }
     }
 
-    /// Load an existing entry file.
+    /// Create an `EntryBuilder` with the given id, same as `EntryBuilder::new()`.
     ///
     /// # Examples
     ///
-    /// ```no_run
+    /// ```
     /// use libsdbootconf::entry::Entry;
     ///
-    /// let entry = Entry::load("/path/to/config").unwrap();
+    /// let entry = Entry::builder("5.12.0-aosc-main").title("AOSC OS").build();
+    ///
+    /// assert_eq!(entry.title(), Some("AOSC OS"));
     /// ```
-    pub fn load<P: AsRef<Path>>(path: P) -> Result<Entry, LibSDBootConfError> {
-        let id = path
-            .as_ref()
-            .file_name()
-            .ok_or_else(|| LibSDBootConfError::InvalidEntryFilename(path.as_ref().to_owned()))?
-            .to_str()
-            .ok_or_else(|| LibSDBootConfError::InvalidEntryFilename(path.as_ref().to_owned()))?
-            .strip_suffix(".conf")
-            .ok_or_else(|| LibSDBootConfError::InvalidEntryFilename(path.as_ref().to_owned()))?;
-        let mut entry = Entry::from_str(&fs::read_to_string(path.as_ref())?)?;
+    pub fn builder<S: Into<String>>(id: S) -> EntryBuilder {
+        EntryBuilder::new(id)
+    }
 
-        entry.id = id.to_owned();
+    /// The filename of the entry under the `entries` directory.
+    ///
+    /// The `.conf` suffix is appended unless the id already ends with it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::Entry;
+    ///
+    /// let entry = Entry::new("5.12.0-aosc-main", Vec::new());
+    /// let suffixed = Entry::new("5.12.0-aosc-main.conf", Vec::new());
+    ///
+    /// assert_eq!(entry.filename(), "5.12.0-aosc-main.conf");
+    /// assert_eq!(suffixed.filename(), "5.12.0-aosc-main.conf");
+    /// ```
+    pub fn filename(&self) -> String {
+        if self.id.ends_with(".conf") {
+            self.id.clone()
+        } else {
+            format!("{}.conf", self.id)
+        }
+    }
 
-        Ok(entry)
+    /// A hash of the serialized entry, as it would be written to disk.
+    ///
+    /// Entries that serialize to the same content have the same hash, regardless of their id.
+    /// The value is stable within a build but may change between Rust releases, so it should
+    /// not be persisted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{Entry, Token};
+    ///
+    /// let a = Entry::new("a", vec![Token::Title("AOSC OS".to_owned())]);
+    /// let b = Entry::new("b", vec![Token::Title("AOSC OS".to_owned())]);
+    ///
+    /// assert_eq!(a.content_hash(), b.content_hash());
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.to_string().hash(&mut hasher);
+
+        hasher.finish()
     }
 
-    /// Save the entry to a file under the given path.
+    /// Parse an entry, rejecting keys not modeled by this crate.
+    ///
+    /// Unlike `Entry::from_str()`, which keeps such keys as `Token::Unknown`, this fails with
+    /// `LibSDBootConfError::InvalidTokenAt` on the first unknown key.
     ///
     /// # Examples
     ///
-    /// ```no_run
+    /// ```
     /// use libsdbootconf::entry::Entry;
-    /// use libsdbootconf::entry::Token;
     ///
-    /// let entry = Entry::new(
-    ///     "5.12.0-aosc-main",
-    ///     vec![Token::Title("5.12.0-aosc-main".to_string())],
+    /// assert!(Entry::parse_strict("title AOSC OS\n").is_ok());
+    /// assert!(Entry::parse_strict("title AOSC OS\nfoo bar\n").is_err());
+    /// ```
+    pub fn parse_strict(s: &str) -> Result<Entry, LibSDBootConfError> {
+        Self::parse(s, true, None)
+    }
+
+    /// Parse an entry from a string like `Entry::from_str()`, mentioning where it has been read
+    /// from on error.
+    ///
+    /// Errors are wrapped in `LibSDBootConfError::ParseErrorIn` with `source` as the path. The id
+    /// is left empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::Entry;
+    /// use std::path::Path;
+    ///
+    /// let error = Entry::parse_named("linux\n", Path::new("/tmp/5.12.0-aosc-main.conf")).unwrap_err();
+    ///
+    /// assert!(error.to_string().contains("/tmp/5.12.0-aosc-main.conf"));
+    /// ```
+    pub fn parse_named(text: &str, source: &Path) -> Result<Entry, LibSDBootConfError> {
+        Entry::from_str(text).map_err(|error| LibSDBootConfError::ParseErrorIn {
+            path: source.to_owned(),
+            source: Box::new(error),
+        })
+    }
+
+    /// Parse an entry from a string like `Entry::from_str()`, but collect the problems found as
+    /// warnings instead of failing.
+    ///
+    /// Lines that fail to parse are skipped. Unknown keys are kept as `Token::Unknown` and
+    /// reported, as well as tokens that may only appear once but are repeated (see
+    /// `Token::is_repeatable()`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{entry::Entry, report::ParseWarning};
+    ///
+    /// let report = Entry::parse_with_report("title AOSC OS\ntitle AOSC OS x86_64\n");
+    ///
+    /// assert_eq!(
+    ///     report.warnings,
+    ///     vec![ParseWarning::DuplicateKey { key: "title".to_owned(), line: 2 }]
     /// );
-    /// entry.write("/path/to/entry").unwrap();
     /// ```
-    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), LibSDBootConfError> {
-        fs::write(path, self.to_string())?;
+    pub fn parse_with_report(s: &str) -> ParseReport<Entry> {
+        let mut warnings = Vec::new();
+        let value = Self::parse(s, false, Some(&mut warnings)).unwrap_or_default();
 
-        Ok(())
+        ParseReport { value, warnings }
     }
-}
 
-/// Builder for `Entry`.
-#[derive(Default, Debug)]
-pub struct EntryBuilder {
-    inner: Entry,
-}
+    /// Parse an entry, failing on unknown keys if `strict` is set, and collecting the problems
+    /// found into `warnings` instead of failing if given.
+    fn parse(
+        s: &str,
+        strict: bool,
+        mut warnings: Option<&mut Vec<ParseWarning>>,
+    ) -> Result<Entry, LibSDBootConfError> {
+        let mut entry = Entry::default();
+        let lines = parser::entry_lines(s.strip_prefix('\u{feff}').unwrap_or(s));
 
-impl EntryBuilder {
-    /// Build an empty `EntryBuilder` with an inner id.
-    pub fn new<S: Into<String>>(id: S) -> Self {
-        Self {
-            inner: Entry::new(id, Vec::new()),
+        for (line_number, line) in lines {
+            let line = line.as_ref();
+
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+
+            let result = line
+                .parse::<Token>()
+                .map_err(|_| match parser::split_key_value(line) {
+                    (key, None) => LibSDBootConfError::MissingValue {
+                        key: key.to_owned(),
+                        line: line_number,
+                    },
+                    _ => LibSDBootConfError::EntryParseErrorAt {
+                        line: line_number,
+                        content: line.to_owned(),
+                    },
+                });
+            let token = match (result, warnings.as_deref_mut()) {
+                (Ok(token), _) => token,
+                (Err(error), Some(warnings)) => {
+                    warnings.push(ParseWarning::InvalidValue {
+                        line: line_number,
+                        message: error.to_string(),
+                    });
+                    continue;
+                }
+                (Err(error), None) => return Err(error),
+            };
+
+            if let (true, Token::Unknown { key, .. }) = (strict, &token) {
+                return Err(LibSDBootConfError::InvalidTokenAt {
+                    token: key.to_owned(),
+                    line: line_number,
+                });
+            }
+
+            if let Some(warnings) = warnings.as_deref_mut() {
+                let (key, _) = parser::split_key_value(line);
+
+                if matches!(token, Token::Unknown { .. }) {
+                    warnings.push(ParseWarning::UnknownKey {
+                        key: key.to_owned(),
+                        line: line_number,
+                    });
+                } else if !token.is_repeatable() && !entry.tokens_of_kind(&token).is_empty() {
+                    warnings.push(ParseWarning::DuplicateKey {
+                        key: key.to_owned(),
+                        line: line_number,
+                    });
+                }
+            }
+
+            entry.tokens.push(token);
         }
+
+        Ok(entry)
     }
 
-    generate_builder_method!(
-        /// Add a `Title` to the inner.
-        token Token::Title INNER(inner) title(S: String)
+    /// Check that the id can be used as the filename of the entry.
+    ///
+    /// The id must not be empty, and must not contain path separators, whitespace, or control
+    /// characters. `util::entry_id()` builds valid ids.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::Entry;
+    ///
+    /// assert!(Entry::new("5.12.0-aosc-main", Vec::new()).validate_id().is_ok());
+    /// assert!(Entry::new("AOSC OS", Vec::new()).validate_id().is_err());
+    /// ```
+    pub fn validate_id(&self) -> Result<(), LibSDBootConfError> {
+        let reason = if self.id.is_empty() {
+            "id is empty"
+        } else if self.id.contains(['/', '\\']) {
+            "id contains a path separator"
+        } else if self.id.contains(char::is_whitespace) {
+            "id contains whitespace"
+        } else if self.id.contains(char::is_control) {
+            "id contains a control character"
+        } else {
+            return Ok(());
+        };
+
+        Err(LibSDBootConfError::InvalidEntryId {
+            id: self.id.clone(),
+            reason,
+        })
+    }
+
+    /// Whether the entry has the given id, ignoring a trailing `.conf` on both sides.
+    pub(crate) fn has_id(&self, id: &str) -> bool {
+        self.id.strip_suffix(".conf").unwrap_or(&self.id) == id.strip_suffix(".conf").unwrap_or(id)
+    }
+
+    /// Replace all tokens of the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{Entry, Token};
+    ///
+    /// let entry = Entry::builder("5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .build()
+    ///     .with_tokens([Token::Version("5.12.0-aosc-main".to_owned())]);
+    ///
+    /// assert_eq!(entry.tokens, vec![Token::Version("5.12.0-aosc-main".to_owned())]);
+    /// ```
+    pub fn with_tokens<T: IntoIterator<Item = Token>>(mut self, tokens: T) -> Self {
+        self.tokens = tokens.into_iter().collect();
+
+        self
+    }
+
+    /// Append a token to the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{Entry, Token};
+    ///
+    /// let mut entry = Entry::new("5.12.0-aosc-main", Vec::new());
+    ///
+    /// entry.push_token(Token::Title("AOSC OS".to_owned()));
+    ///
+    /// assert_eq!(entry.title(), Some("AOSC OS"));
+    /// ```
+    pub fn push_token(&mut self, token: Token) {
+        self.tokens.push(token);
+    }
+
+    /// Remove every token for which `predicate` returns `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, Token};
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .initrd("/initramfs-5.12.0-aosc-main.img")
+    ///     .build();
+    ///
+    /// entry.remove_tokens_matching(|token| matches!(token, Token::Initrd(_)));
+    ///
+    /// assert!(entry.initrds().is_empty());
+    /// ```
+    pub fn remove_tokens_matching<F: Fn(&Token) -> bool>(&mut self, predicate: F) {
+        self.tokens.retain(|token| !predicate(token));
+    }
+
+    /// Get all tokens of the same kind as `kind`, in order. The value of `kind` is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, Token};
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .options("quiet")
+    ///     .options("rw")
+    ///     .build();
+    ///
+    /// assert_eq!(entry.tokens_of_kind(&Token::Options(String::new())).len(), 2);
+    /// ```
+    pub fn tokens_of_kind(&self, kind: &Token) -> Vec<&Token> {
+        let kind = mem::discriminant(kind);
+
+        self.tokens
+            .iter()
+            .filter(|token| mem::discriminant(*token) == kind)
+            .collect()
+    }
+
+    /// Replace the first token of the same kind as `token` in place, removing the other tokens of
+    /// that kind, or append `token` if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, Token};
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .version("5.12.0-aosc-main")
+    ///     .build();
+    ///
+    /// entry.set_token(Token::Title("AOSC OS x86_64".to_owned()));
+    ///
+    /// assert_eq!(entry.tokens[0], Token::Title("AOSC OS x86_64".to_owned()));
+    /// ```
+    pub fn set_token(&mut self, token: Token) {
+        let kind = mem::discriminant(&token);
+        let mut replacement = Some(token);
+
+        self.tokens = mem::take(&mut self.tokens)
+            .into_iter()
+            .filter_map(|token| {
+                if mem::discriminant(&token) == kind {
+                    replacement.take()
+                } else {
+                    Some(token)
+                }
+            })
+            .collect();
+        self.tokens.extend(replacement);
+    }
+
+    generate_token_method!(
+        /// Set the `Title`, replacing the existing one in place.
+        set Token::Title set_title(S: String)
     );
-    generate_builder_method!(
-        /// Add a `Version` to the inner.
-        token Token::Version INNER(inner) version(S: String)
+    generate_token_method!(
+        /// Set the `Version`, replacing the existing one in place.
+        set Token::Version set_version(S: String)
     );
-    generate_builder_method!(
-        /// Add a `MachineID` to the inner.
-        token Token::MachineID INNER(inner) machine_id(S: String)
+    generate_token_method!(
+        /// Set the `MachineID`, replacing the existing one in place.
+        set Token::MachineID set_machine_id(S: String)
     );
-    generate_builder_method!(
-        /// Add an `Efi` to the inner.
-        token Token::Efi INNER(inner) efi(P: PathBuf)
+    generate_token_method!(
+        /// Set the `Efi`, replacing the existing one in place.
+        set Token::Efi set_efi(P: PathBuf)
     );
-    generate_builder_method!(
-        /// Add an `Options` to the inner.
-        token Token::Options INNER(inner) options(S: String)
+    generate_token_method!(
+        /// Set the `Options`, replacing the existing ones in place.
+        set Token::Options set_options(S: String)
     );
-    generate_builder_method!(
-        /// Add a `Linux` to the inner.
-        token Token::Linux INNER(inner) linux(P: PathBuf)
+    generate_token_method!(
+        /// Set the `Linux`, replacing the existing one in place.
+        set Token::Linux set_linux(P: PathBuf)
     );
-    generate_builder_method!(
-        /// Add an `Initrd` to the inner.
-        token Token::Initrd INNER(inner) initrd(P: PathBuf)
+    generate_token_method!(
+        /// Set the `DeviceTree`, replacing the existing one in place.
+        set Token::DeviceTree set_devicetree(P: PathBuf)
+    );
+    generate_token_method!(
+        /// Set the `Architecture`, replacing the existing one in place.
+        set Token::Architecture set_architecture(S: String)
+    );
+    generate_token_method!(
+        /// Set the `SortKey`, replacing the existing one in place.
+        set Token::SortKey set_sort_key(S: String)
     );
 
-    /// Build the `Entry`.
-    pub fn build(self) -> Entry {
-        self.inner
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_builder() {
+    generate_token_method!(
+        /// Get the value of the first `Title`.
+        get Token::Title title() -> &str
+    );
+    generate_token_method!(
+        /// Get the value of the first `Version`.
+        get Token::Version version() -> &str
+    );
+    generate_token_method!(
+        /// Get the value of the first `MachineID`.
+        get Token::MachineID machine_id() -> &str
+    );
+    generate_token_method!(
+        /// Get the path of the first `Efi`.
+        get Token::Efi efi() -> &Path
+    );
+    generate_token_method!(
+        /// Get the value of the first `Options`.
+        ///
+        /// Only the first `Options` token is considered.
+        get Token::Options options() -> &str
+    );
+    generate_token_method!(
+        /// Get the path of the first `Linux`.
+        get Token::Linux linux() -> &Path
+    );
+    generate_token_method!(
+        /// Get the path of the first `DeviceTree`.
+        get Token::DeviceTree devicetree() -> &Path
+    );
+    generate_token_method!(
+        /// Get the value of the first `Architecture`.
+        get Token::Architecture architecture() -> &str
+    );
+    generate_token_method!(
+        /// Get the value of the first `SortKey`.
+        get Token::SortKey sort_key() -> &str
+    );
+
+    /// Classify the entry by what it boots.
+    ///
+    /// An entry with a `linux` token is `EntryKind::Linux`, even if it also has an `efi` token.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, EntryKind};
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .linux("/vmlinuz-5.12.0-aosc-main")
+    ///     .build();
+    ///
+    /// assert_eq!(entry.kind(), EntryKind::Linux);
+    /// ```
+    pub fn kind(&self) -> EntryKind {
+        if self.linux().is_some() {
+            EntryKind::Linux
+        } else if self.efi().is_some() {
+            EntryKind::Efi
+        } else {
+            EntryKind::Unknown
+        }
+    }
+
+    /// Get the paths of all `Initrd` tokens, in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    /// use std::path::PathBuf;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .initrd("/intel-ucode.img")
+    ///     .initrd("/initramfs-5.12.0-aosc-main.img")
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     entry.initrds(),
+    ///     vec![
+    ///         &PathBuf::from("/intel-ucode.img"),
+    ///         &PathBuf::from("/initramfs-5.12.0-aosc-main.img"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn initrds(&self) -> Vec<&PathBuf> {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Initrd(initrd) => Some(initrd),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Get the kernel parameters of all `Options` tokens, in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .options("root=/dev/sda1  rw")
+    ///     .options("quiet")
+    ///     .build();
+    ///
+    /// assert_eq!(entry.options_list(), vec!["root=/dev/sda1", "rw", "quiet"]);
+    /// ```
+    pub fn options_list(&self) -> Vec<&str> {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Options(options) => Some(options.split_whitespace()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Get the kernel command line systemd-boot would pass, with the values of all `Options`
+    /// tokens joined with a space in order.
+    ///
+    /// Returns `None` if there is no `Options` token.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .options("root=/dev/sda1 rw")
+    ///     .options("quiet")
+    ///     .build();
+    ///
+    /// assert_eq!(entry.effective_options().as_deref(), Some("root=/dev/sda1 rw quiet"));
+    /// ```
+    pub fn effective_options(&self) -> Option<String> {
+        let options = self
+            .tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Options(options) => Some(options.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        (!options.is_empty()).then(|| options.join(" "))
+    }
+
+    /// Append a kernel parameter to the last `Options` token, creating one if there is none.
+    ///
+    /// The parameter is not added again if it is already present in any `Options` token. Returns
+    /// whether the parameter has been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{Entry, Token};
+    ///
+    /// let mut entry = Entry::new("5.12.0-aosc-main", Vec::new());
+    ///
+    /// assert!(entry.add_option("quiet"));
+    /// assert!(!entry.add_option("quiet"));
+    /// assert_eq!(entry.tokens, vec![Token::Options("quiet".to_owned())]);
+    /// ```
+    pub fn add_option(&mut self, param: &str) -> bool {
+        if self.options_list().contains(&param) {
+            return false;
+        }
+
+        let last = self.tokens.iter_mut().rev().find_map(|token| match token {
+            Token::Options(options) => Some(options),
+            _ => None,
+        });
+
+        match last {
+            Some(options) if options.trim().is_empty() => *options = param.to_owned(),
+            Some(options) => {
+                options.push(' ');
+                options.push_str(param);
+            }
+            None => self.tokens.push(Token::Options(param.to_owned())),
+        }
+
+        true
+    }
+
+    /// Remove every occurrence of a kernel parameter from the `Options` tokens.
+    ///
+    /// `Options` tokens without the parameter are left untouched, and the ones left with no
+    /// parameters are removed. Returns whether the parameter has been found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, Token};
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .options("root=/dev/sda1 quiet rw")
+    ///     .build();
+    ///
+    /// assert!(entry.remove_option("quiet"));
+    /// assert_eq!(entry.tokens, vec![Token::Options("root=/dev/sda1 rw".to_owned())]);
+    /// ```
+    pub fn remove_option(&mut self, param: &str) -> bool {
+        let mut found = false;
+
+        self.tokens = mem::take(&mut self.tokens)
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Options(options) if options.split_whitespace().any(|p| p == param) => {
+                    found = true;
+
+                    let kept = options
+                        .split_whitespace()
+                        .filter(|p| *p != param)
+                        .collect::<Vec<_>>();
+
+                    (!kept.is_empty()).then(|| Token::Options(kept.join(" ")))
+                }
+                token => Some(token),
+            })
+            .collect();
+
+        found
+    }
+
+    /// Remove duplicated tokens that may only appear once, keeping the last occurrence of each.
+    ///
+    /// Repeatable tokens (see `Token::is_repeatable()`) are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, Token};
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .title("AOSC OS x86_64")
+    ///     .build();
+    ///
+    /// entry.dedup_singletons();
+    ///
+    /// assert_eq!(entry.tokens, vec![Token::Title("AOSC OS x86_64".to_owned())]);
+    /// ```
+    pub fn dedup_singletons(&mut self) {
+        let kinds = self
+            .tokens
+            .iter()
+            .map(mem::discriminant)
+            .collect::<Vec<_>>();
+
+        self.tokens = mem::take(&mut self.tokens)
+            .into_iter()
+            .enumerate()
+            .filter(|(index, token)| {
+                token.is_repeatable() || !kinds[index + 1..].contains(&kinds[*index])
+            })
+            .map(|(_, token)| token)
+            .collect();
+    }
+
+    /// Sort the tokens into the conventional order of systemd-boot entries.
+    ///
+    /// The order is `title`, `version`, `machine-id`, `sort-key`, `architecture`, `options`,
+    /// `linux`, `initrd`, `efi`, `devicetree`, `devicetree-overlay`, then unknown keys. Tokens of
+    /// the same kind, such as multiple `initrd` lines, keep their relative order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, Token};
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .linux("/vmlinuz-5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .build();
+    ///
+    /// entry.normalize_token_order();
+    ///
+    /// assert_eq!(entry.tokens[0], Token::Title("AOSC OS".to_owned()));
+    /// ```
+    pub fn normalize_token_order(&mut self) {
+        self.tokens.sort_by_key(Token::canonical_position);
+    }
+
+    /// Replace all `Initrd` tokens with the given paths, in order.
+    ///
+    /// The new `Initrd` tokens are inserted where the first existing one was, or appended if the
+    /// entry had no `Initrd` token. Microcode initrds must come before the main initramfs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{Entry, Token};
+    /// use std::path::PathBuf;
+    ///
+    /// let mut entry = Entry::new("5.12.0-aosc-main", Vec::new());
+    ///
+    /// entry.set_initrds([
+    ///     PathBuf::from("/intel-ucode.img"),
+    ///     PathBuf::from("/initramfs-5.12.0-aosc-main.img"),
+    /// ]);
+    ///
+    /// assert_eq!(entry.tokens[0], Token::Initrd(PathBuf::from("/intel-ucode.img")));
+    /// ```
+    pub fn set_initrds<I: IntoIterator<Item = PathBuf>>(&mut self, initrds: I) {
+        let position = self
+            .tokens
+            .iter()
+            .position(|token| matches!(token, Token::Initrd(_)))
+            .unwrap_or(self.tokens.len());
+
+        self.tokens
+            .retain(|token| !matches!(token, Token::Initrd(_)));
+        self.tokens
+            .splice(position..position, initrds.into_iter().map(Token::Initrd));
+    }
+
+    /// Stably sort the `Initrd` tokens by the priority returned by `key`, lowest first.
+    ///
+    /// The sorted paths are put back into the positions of the existing `Initrd` tokens, so other
+    /// tokens are left in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    /// use std::path::Path;
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .initrd("/initramfs-5.12.0-aosc-main.img")
+    ///     .initrd("/intel-ucode.img")
+    ///     .build();
+    ///
+    /// entry.reorder_initrds(|path| if path.ends_with("intel-ucode.img") { 0 } else { 1 });
+    ///
+    /// assert_eq!(entry.initrds()[0], Path::new("/intel-ucode.img"));
+    /// ```
+    pub fn reorder_initrds<F: Fn(&PathBuf) -> i32>(&mut self, key: F) {
+        let mut initrds = self
+            .tokens
+            .iter_mut()
+            .filter_map(|token| match token {
+                Token::Initrd(path) => Some(mem::take(path)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        initrds.sort_by_key(|path| key(path));
+
+        let paths = self.tokens.iter_mut().filter_map(|token| match token {
+            Token::Initrd(path) => Some(path),
+            _ => None,
+        });
+
+        for (path, initrd) in paths.zip(initrds) {
+            *path = initrd;
+        }
+    }
+
+    /// Remove duplicated kernel parameters from the `Options` tokens with the default
+    /// `DedupPolicy`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{EntryBuilder, Token};
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .options("root=/dev/sda1 quiet")
+    ///     .options("root=/dev/sda2 quiet")
+    ///     .build();
+    ///
+    /// entry.dedup_options();
+    ///
+    /// assert_eq!(entry.tokens, vec![
+    ///     Token::Options("quiet".to_owned()),
+    ///     Token::Options("root=/dev/sda2".to_owned()),
+    /// ]);
+    /// ```
+    pub fn dedup_options(&mut self) {
+        self.dedup_options_with(DedupPolicy::default());
+    }
+
+    /// Remove duplicated kernel parameters from the `Options` tokens with a `DedupPolicy`.
+    ///
+    /// Parameters of all `Options` tokens are considered as a single kernel command line.
+    /// `Options` tokens left with no parameters are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{DedupPolicy, EntryBuilder, Precedence, Token};
+    ///
+    /// let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .options("root=/dev/sda1 root=/dev/sda2")
+    ///     .build();
+    ///
+    /// entry.dedup_options_with(DedupPolicy {
+    ///     key_value: Precedence::First,
+    ///     flags: Precedence::First,
+    /// });
+    ///
+    /// assert_eq!(entry.tokens, vec![Token::Options("root=/dev/sda1".to_owned())]);
+    /// ```
+    pub fn dedup_options_with(&mut self, policy: DedupPolicy) {
+        let params = self
+            .tokens
+            .iter()
+            .enumerate()
+            .filter_map(|(index, token)| match token {
+                Token::Options(options) => Some((index, options)),
+                _ => None,
+            })
+            .flat_map(|(index, options)| {
+                options.split_whitespace().map(move |param| (index, param))
+            })
+            .collect::<Vec<_>>();
+        let duplicated = |a: &str, b: &str| match (a.split_once('='), b.split_once('=')) {
+            (Some((a, _)), Some((b, _))) => a == b,
+            (None, None) => a == b,
+            _ => false,
+        };
+        let mut kept = vec![Vec::new(); self.tokens.len()];
+
+        for (n, &(index, param)) in params.iter().enumerate() {
+            let precedence = if param.contains('=') {
+                policy.key_value
+            } else {
+                policy.flags
+            };
+            let others = match precedence {
+                Precedence::First => &params[..n],
+                Precedence::Last => &params[n + 1..],
+            };
+
+            if !others.iter().any(|&(_, other)| duplicated(param, other)) {
+                kept[index].push(param.to_owned());
+            }
+        }
+
+        self.tokens = std::mem::take(&mut self.tokens)
+            .into_iter()
+            .zip(kept)
+            .filter_map(|(token, kept)| match token {
+                Token::Options(options) if !options.trim().is_empty() => {
+                    (!kept.is_empty()).then(|| Token::Options(kept.join(" ")))
+                }
+                token => Some(token),
+            })
+            .collect();
+    }
+
+    /// Check that the files referenced by the `Linux`, `Initrd`, `Efi`, `DeviceTree` and
+    /// `DeviceTreeOverlay` tokens exist on the ESP mounted at `esp_root`.
+    ///
+    /// Paths are treated as relative to `esp_root`, even if they start with `/`. Returns the paths
+    /// that do not exist, in the order of the tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::Entry;
+    /// use std::path::Path;
+    ///
+    /// let entry = Entry::load("/efi/loader/entries/5.12.0-aosc-main.conf").unwrap();
+    ///
+    /// if let Err(missing) = entry.validate_paths(Path::new("/efi")) {
+    ///     println!("missing files: {:?}", missing);
+    /// }
+    /// ```
+    pub fn validate_paths(&self, esp_root: &Path) -> Result<(), Vec<PathBuf>> {
+        let missing = self
+            .tokens
+            .iter()
+            .flat_map(|token| match token {
+                Token::Efi(path)
+                | Token::Linux(path)
+                | Token::Initrd(path)
+                | Token::DeviceTree(path) => vec![path],
+                Token::DeviceTreeOverlay(paths) => paths.iter().collect(),
+                _ => Vec::new(),
+            })
+            .filter(|path| {
+                !esp_root
+                    .join(path.strip_prefix("/").unwrap_or(path))
+                    .exists()
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// Check the paths of the `Linux`, `Initrd`, `Efi`, `DeviceTree` and `DeviceTreeOverlay`
+    /// tokens for likely mistakes.
+    ///
+    /// This is advisory only, entries with lints are still loaded and written as-is. Returns the
+    /// lints in the order of the tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{entry::{Entry, Token}, report::EntryLint};
+    /// use std::path::PathBuf;
+    ///
+    /// let entry = Entry::new("5.12.0-aosc-main", vec![Token::Linux(PathBuf::from("vmlinuz"))]);
+    ///
+    /// assert_eq!(
+    ///     entry.lint(),
+    ///     vec![EntryLint::RelativePath { key: "linux", path: PathBuf::from("vmlinuz") }]
+    /// );
+    /// ```
+    pub fn lint(&self) -> Vec<EntryLint> {
+        self.tokens
+            .iter()
+            .flat_map(|token| match token {
+                Token::Efi(path) => vec![("efi", path)],
+                Token::Linux(path) => vec![("linux", path)],
+                Token::Initrd(path) => vec![("initrd", path)],
+                Token::DeviceTree(path) => vec![("devicetree", path)],
+                Token::DeviceTreeOverlay(paths) => paths
+                    .iter()
+                    .map(|path| ("devicetree-overlay", path))
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .flat_map(|(key, path)| {
+                let raw = path.to_string_lossy();
+                let mut lints = Vec::new();
+
+                if raw.contains('\\') {
+                    lints.push(EntryLint::BackslashPath {
+                        key,
+                        path: path.clone(),
+                    });
+                }
+                if !raw.starts_with('/') && !raw.starts_with('\\') {
+                    lints.push(EntryLint::RelativePath {
+                        key,
+                        path: path.clone(),
+                    });
+                }
+
+                lints
+            })
+            .collect()
+    }
+
+    /// Group the values of the tokens by their keys, see `Token::key()` and
+    /// `Token::value_string()`.
+    ///
+    /// Values of the same key, such as multiple `initrd` lines, keep their order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .initrd("/intel-ucode.img")
+    ///     .initrd("/initramfs-5.12.0-aosc-main.img")
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     entry.to_map()["initrd"],
+    ///     vec!["/intel-ucode.img", "/initramfs-5.12.0-aosc-main.img"]
+    /// );
+    /// ```
+    pub fn to_map(&self) -> BTreeMap<String, Vec<String>> {
+        let mut map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for token in &self.tokens {
+            map.entry(token.key().to_owned())
+                .or_default()
+                .push(token.value_string());
+        }
+
+        map
+    }
+
+    /// Build an entry from values grouped by their keys, the reverse of `Entry::to_map()`.
+    ///
+    /// The tokens are sorted with `Entry::normalize_token_order()`, and values of the same key
+    /// keep their order. Fails with `LibSDBootConfError::InvalidTokenValue` on the first key or
+    /// value that cannot be written as a line of an entry file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::{Entry, EntryBuilder};
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .linux("/vmlinuz-5.12.0-aosc-main")
+    ///     .build();
+    ///
+    /// assert_eq!(Entry::from_map("5.12.0-aosc-main", entry.to_map()).unwrap(), entry);
+    /// ```
+    pub fn from_map<S: Into<String>>(
+        id: S,
+        map: BTreeMap<String, Vec<String>>,
+    ) -> Result<Entry, LibSDBootConfError> {
+        let mut entry = Entry::new(id, Vec::new());
+
+        for (key, values) in map {
+            if key.is_empty() || key.contains(char::is_whitespace) || key.starts_with('#') {
+                return Err(LibSDBootConfError::InvalidTokenValue {
+                    key,
+                    reason: "invalid key",
+                });
+            }
+
+            for value in values {
+                check_token_value(&key, &value)?;
+                entry
+                    .tokens
+                    .push(format!("{} {}", key, value.trim()).parse()?);
+            }
+        }
+
+        entry.normalize_token_order();
+
+        Ok(entry)
+    }
+
+    /// Export the entry as shell variable assignments that can be sourced by a shell script.
+    ///
+    /// Every token is exported as `SDBOOT_<KEY>`, e.g. `SDBOOT_TITLE` or `SDBOOT_MACHINE_ID`, and
+    /// the id as `SDBOOT_ID`. Unknown keys are exported as `SDBOOT_X_<KEY>` so that they can never
+    /// overwrite the id or a known key, with characters that are not ASCII alphanumerics replaced
+    /// with `_`. Values of repeatable tokens such as `initrd` are joined with spaces into a single
+    /// variable, while a repeated singleton such as `title` takes its last value like
+    /// `Entry::dedup_singletons()`. Values are single-quoted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .title("AOSC OS")
+    ///     .initrd("/intel-ucode.img")
+    ///     .initrd("/initramfs-5.12.0-aosc-main.img")
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     entry.to_env(),
+    ///     "SDBOOT_ID='5.12.0-aosc-main'\n\
+    ///      SDBOOT_TITLE='AOSC OS'\n\
+    ///      SDBOOT_INITRD='/intel-ucode.img /initramfs-5.12.0-aosc-main.img'\n"
+    /// );
+    /// ```
+    pub fn to_env(&self) -> String {
+        let mut variables = vec![("SDBOOT_ID".to_owned(), vec![self.id.clone()])];
+
+        for token in &self.tokens {
+            let prefix = match token {
+                Token::Unknown { .. } => "SDBOOT_X_",
+                _ => "SDBOOT_",
+            };
+            let name = format!(
+                "{}{}",
+                prefix,
+                token
+                    .key()
+                    .chars()
+                    .map(|c| match c {
+                        'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+                        _ => '_',
+                    })
+                    .collect::<String>()
+            );
+            let value = token.value_string();
+
+            match variables.iter_mut().find(|(variable, _)| *variable == name) {
+                Some((_, values)) if token.is_repeatable() => values.push(value),
+                Some((_, values)) => *values = vec![value],
+                None => variables.push((name, vec![value])),
+            }
+        }
+
+        variables
+            .into_iter()
+            .map(|(name, values)| format!("{}={}\n", name, shell_quote(&values.join(" "))))
+            .collect()
+    }
+
+    /// Read an entry from a reader, such as a network stream or a file in an archive.
+    ///
+    /// The id of the entry is left empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::Entry;
+    /// use std::io::Cursor;
+    ///
+    /// let entry = Entry::from_reader(Cursor::new(b"title AOSC OS\n")).unwrap();
+    ///
+    /// assert_eq!(entry.title(), Some("AOSC OS"));
+    /// ```
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Entry, LibSDBootConfError> {
+        let mut content = String::new();
+
+        reader.read_to_string(&mut content)?;
+
+        Entry::from_str(&content)
+    }
+
+    /// Load an existing entry file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::Entry;
+    ///
+    /// let entry = Entry::load("/path/to/config").unwrap();
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Entry, LibSDBootConfError> {
+        let id = Self::id_from_path(path.as_ref())?;
+        let mut entry = Entry::from_str(&util::read_to_string(path.as_ref())?)?;
+
+        entry.id = id.to_owned();
+
+        Ok(entry)
+    }
+
+    /// Load an existing entry file, rejecting keys not modeled by this crate.
+    ///
+    /// See `Entry::parse_strict()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::Entry;
+    ///
+    /// let entry = Entry::load_strict("/path/to/config").unwrap();
+    /// ```
+    pub fn load_strict<P: AsRef<Path>>(path: P) -> Result<Entry, LibSDBootConfError> {
+        let id = Self::id_from_path(path.as_ref())?;
+        let mut entry = Entry::parse_strict(&util::read_to_string(path.as_ref())?)?;
+
+        entry.id = id.to_owned();
+
+        Ok(entry)
+    }
+
+    /// Load an existing entry file like `load`, but collect the problems found as warnings
+    /// instead of failing, see `Entry::parse_with_report()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::Entry;
+    ///
+    /// let report = Entry::load_with_report("/path/to/entry.conf").unwrap();
+    ///
+    /// for warning in report.warnings {
+    ///     println!("{:?}", warning);
+    /// }
+    /// ```
+    pub fn load_with_report<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<ParseReport<Entry>, LibSDBootConfError> {
+        let id = Self::id_from_path(path.as_ref())?;
+        let mut report = Entry::parse_with_report(&util::read_to_string(path.as_ref())?);
+
+        report.value.id = id.to_owned();
+
+        Ok(report)
+    }
+
+    /// Get the id of an entry from the path of its file.
+    pub(crate) fn id_from_path(path: &Path) -> Result<&str, LibSDBootConfError> {
+        path.file_name()
+            .ok_or_else(|| LibSDBootConfError::InvalidEntryFilename(path.to_owned()))?
+            .to_str()
+            .ok_or_else(|| LibSDBootConfError::InvalidEntryFilename(path.to_owned()))?
+            .strip_suffix(".conf")
+            .ok_or_else(|| LibSDBootConfError::InvalidEntryFilename(path.to_owned()))
+    }
+
+    /// Save the entry to a file under the given path.
+    ///
+    /// The entry is written to a temporary file in the same directory first, which then replaces
+    /// the file, so that an interrupted write never leaves a truncated file behind.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::Entry;
+    /// use libsdbootconf::entry::Token;
+    ///
+    /// let entry = Entry::new(
+    ///     "5.12.0-aosc-main",
+    ///     vec![Token::Title("5.12.0-aosc-main".to_string())],
+    /// );
+    /// entry.write("/path/to/entry").unwrap();
+    /// ```
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), LibSDBootConfError> {
+        util::write_atomic(path, &self.to_string())?;
+
+        Ok(())
+    }
+
+    /// Save the entry to a file like `write`, with a comment header before the first token.
+    ///
+    /// Every line of `header` is prefixed with `# ` unless it already starts with `#`. Comments are
+    /// skipped when loading, so the header is not kept by a subsequent load and write.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main").title("AOSC OS").build();
+    /// entry
+    ///     .write_with_header("/path/to/entry", "Generated by aosc-installer, do not edit")
+    ///     .unwrap();
+    /// ```
+    pub fn write_with_header<P: AsRef<Path>>(
+        &self,
+        path: P,
+        header: &str,
+    ) -> Result<(), LibSDBootConfError> {
+        util::write_atomic(path, &(util::comment_header(header) + &self.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Save the entry to a file under the given path by truncating and writing to it directly.
+    ///
+    /// Unlike `write`, the file may be left truncated if the write is interrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::Entry;
+    /// use libsdbootconf::entry::Token;
+    ///
+    /// let entry = Entry::new(
+    ///     "5.12.0-aosc-main",
+    ///     vec![Token::Title("5.12.0-aosc-main".to_string())],
+    /// );
+    /// entry.write_non_atomic("/path/to/entry").unwrap();
+    /// ```
+    pub fn write_non_atomic<P: AsRef<Path>>(&self, path: P) -> Result<(), LibSDBootConfError> {
+        fs::write(path, self.to_string())?;
+
+        Ok(())
+    }
+}
+
+/// Check that a value can be written on the line of its key in an entry file.
+fn check_token_value(key: &str, value: &str) -> Result<(), LibSDBootConfError> {
+    let reason = if value.trim().is_empty() {
+        "empty value"
+    } else if value.contains(['\n', '\r']) {
+        "value spans multiple lines"
+    } else {
+        return Ok(());
+    };
+
+    Err(LibSDBootConfError::InvalidTokenValue {
+        key: key.to_owned(),
+        reason,
+    })
+}
+
+/// Quote a string with single quotes for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// A `Token` with a comment written on the lines preceding it, see `CommentedEntry`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommentedToken {
+    /// The comment, written with every line prefixed with `# `.
+    pub comment: Option<String>,
+    /// The token.
+    pub token: Token,
+}
+
+impl From<Token> for CommentedToken {
+    fn from(token: Token) -> Self {
+        Self {
+            comment: None,
+            token,
+        }
+    }
+}
+
+impl fmt::Display for CommentedToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(comment) = &self.comment {
+            f.write_str(&util::comment_header(comment))?;
+        }
+
+        write!(f, "{}", self.token)
+    }
+}
+
+/// An entry with comments on its tokens, for generating self-documenting entry files.
+///
+/// Every comment is stored along with its token, so it stays with the token when tokens are
+/// added, removed or reordered. Comments are not read back by the parser, an `Entry` parsed from a
+/// written `CommentedEntry` equals the `Entry` converted from it.
+///
+/// # Examples
+///
+/// ```
+/// use libsdbootconf::entry::{CommentedEntry, Entry, EntryBuilder, Token};
+/// use std::path::PathBuf;
+///
+/// let mut entry = CommentedEntry::from(EntryBuilder::new("5.12.0-aosc-main").title("AOSC OS").build());
+///
+/// entry.push_commented_token(Token::Linux(PathBuf::from("/vmlinuz")), "fallback kernel");
+///
+/// assert_eq!(entry.to_string(), "title AOSC OS\n# fallback kernel\nlinux /vmlinuz\n");
+/// ```
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommentedEntry {
+    /// The ID of the entry, see `Entry::id`.
+    pub id: String,
+    /// The fields of the entry along with their comments.
+    pub tokens: Vec<CommentedToken>,
+}
+
+impl From<Entry> for CommentedEntry {
+    fn from(entry: Entry) -> Self {
+        Self {
+            id: entry.id,
+            tokens: entry.tokens.into_iter().map(CommentedToken::from).collect(),
+        }
+    }
+}
+
+/// Drop the comments of a `CommentedEntry`.
+impl From<CommentedEntry> for Entry {
+    fn from(entry: CommentedEntry) -> Self {
+        Entry::new(entry.id, entry.tokens.into_iter().map(|token| token.token))
+    }
+}
+
+impl fmt::Display for CommentedEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "{}", token)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommentedEntry {
+    /// Create an empty `CommentedEntry` with the given id.
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        Self {
+            id: id.into(),
+            tokens: Vec::new(),
+        }
+    }
+
+    /// Push a token without a comment.
+    pub fn push_token(&mut self, token: Token) {
+        self.tokens.push(token.into());
+    }
+
+    /// Push a token with a comment written on the lines preceding it.
+    pub fn push_commented_token<S: Into<String>>(&mut self, token: Token, comment: S) {
+        self.tokens.push(CommentedToken {
+            comment: Some(comment.into()),
+            token,
+        });
+    }
+
+    /// Write the entry to a file atomically, like `Entry::write()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::entry::{CommentedEntry, Token};
+    ///
+    /// let mut entry = CommentedEntry::new("5.12.0-aosc-main");
+    ///
+    /// entry.push_commented_token(Token::Title("AOSC OS".to_owned()), "generated by aosc-installer");
+    /// entry.write("/efi/loader/entries/5.12.0-aosc-main.conf").unwrap();
+    /// ```
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), LibSDBootConfError> {
+        util::write_atomic(path, &self.to_string())?;
+
+        Ok(())
+    }
+}
+
+/// Builder for `Entry`.
+#[derive(Default, Debug)]
+pub struct EntryBuilder {
+    inner: Entry,
+}
+
+impl EntryBuilder {
+    /// Build an empty `EntryBuilder` with an inner id.
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        Self {
+            inner: Entry::new(id, Vec::new()),
+        }
+    }
+
+    generate_builder_method!(
+        /// Add a `Title` to the inner.
+        token Token::Title INNER(inner) title(S: String)
+    );
+    generate_builder_method!(
+        /// Add a `Version` to the inner.
+        token Token::Version INNER(inner) version(S: String)
+    );
+    generate_builder_method!(
+        /// Add a `MachineID` to the inner.
+        token Token::MachineID INNER(inner) machine_id(S: String)
+    );
+    generate_builder_method!(
+        /// Add an `Efi` to the inner.
+        token Token::Efi INNER(inner) efi(P: PathBuf)
+    );
+    generate_builder_method!(
+        /// Add an `Options` to the inner.
+        token Token::Options INNER(inner) options(S: String)
+    );
+    generate_builder_method!(
+        /// Add a `Linux` to the inner.
+        token Token::Linux INNER(inner) linux(P: PathBuf)
+    );
+    generate_builder_method!(
+        /// Add an `Initrd` to the inner.
+        token Token::Initrd INNER(inner) initrd(P: PathBuf)
+    );
+
+    generate_builder_method!(
+        /// Add a `DeviceTree` to the inner.
+        token Token::DeviceTree INNER(inner) devicetree(P: PathBuf)
+    );
+
+    generate_builder_method!(
+        /// Add an `Architecture` to the inner.
+        token Token::Architecture INNER(inner) architecture(S: String)
+    );
+
+    generate_builder_method!(
+        /// Add a `SortKey` to the inner.
+        token Token::SortKey INNER(inner) sort_key(S: String)
+    );
+
+    generate_builder_method!(
+        /// Add a `Title` to the inner if the value is `Some`.
+        token_option Token::Title INNER(inner) title_opt(S: String)
+    );
+    generate_builder_method!(
+        /// Add a `Version` to the inner if the value is `Some`.
+        token_option Token::Version INNER(inner) version_opt(S: String)
+    );
+    generate_builder_method!(
+        /// Add a `MachineID` to the inner if the value is `Some`.
+        token_option Token::MachineID INNER(inner) machine_id_opt(S: String)
+    );
+    generate_builder_method!(
+        /// Add an `Efi` to the inner if the value is `Some`.
+        token_option Token::Efi INNER(inner) efi_opt(P: PathBuf)
+    );
+    generate_builder_method!(
+        /// Add an `Options` to the inner if the value is `Some`.
+        token_option Token::Options INNER(inner) options_opt(S: String)
+    );
+    generate_builder_method!(
+        /// Add a `Linux` to the inner if the value is `Some`.
+        token_option Token::Linux INNER(inner) linux_opt(P: PathBuf)
+    );
+    generate_builder_method!(
+        /// Add an `Initrd` to the inner if the value is `Some`.
+        token_option Token::Initrd INNER(inner) initrd_opt(P: PathBuf)
+    );
+    generate_builder_method!(
+        /// Add a `DeviceTree` to the inner if the value is `Some`.
+        token_option Token::DeviceTree INNER(inner) devicetree_opt(P: PathBuf)
+    );
+    generate_builder_method!(
+        /// Add an `Architecture` to the inner if the value is `Some`.
+        token_option Token::Architecture INNER(inner) architecture_opt(S: String)
+    );
+    generate_builder_method!(
+        /// Add a `SortKey` to the inner if the value is `Some`.
+        token_option Token::SortKey INNER(inner) sort_key_opt(S: String)
+    );
+
+    /// Add a `Title` to the inner in the conventional `<PRETTY_NAME> (<version>)` form, from the
+    /// `PRETTY_NAME` field of `os-release` and a kernel version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .title_from_os_release("AOSC OS", "5.12.0-aosc-main")
+    ///     .build();
+    ///
+    /// assert_eq!(entry.title(), Some("AOSC OS (5.12.0-aosc-main)"));
+    /// ```
+    pub fn title_from_os_release(mut self, pretty_name: &str, version: &str) -> Self {
+        self.inner
+            .tokens
+            .push(Token::Title(format!("{} ({})", pretty_name, version)));
+
+        self
+    }
+
+    /// Add a `DeviceTreeOverlay` with the given paths to the inner.
+    pub fn devicetree_overlay<I: IntoIterator<Item = PathBuf>>(mut self, overlays: I) -> Self {
+        self.inner
+            .tokens
+            .push(Token::DeviceTreeOverlay(overlays.into_iter().collect()));
+
+        self
+    }
+
+    /// Add an `Options` to the inner with the given kernel parameters joined with spaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// let entry = EntryBuilder::new("5.12.0-aosc-main")
+    ///     .options_from(["root=/dev/sda1", "rw", "quiet"])
+    ///     .build();
+    ///
+    /// assert_eq!(entry.to_string(), "options root=/dev/sda1 rw quiet\n");
+    /// ```
+    pub fn options_from<I, S>(mut self, params: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let options = params
+            .into_iter()
+            .map(|param| param.as_ref().to_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        self.inner.tokens.push(Token::Options(options));
+
+        self
+    }
+
+    /// Add an `Initrd` to the inner for each path.
+    pub fn initrds<I: IntoIterator<Item = PathBuf>>(mut self, initrds: I) -> Self {
+        self.inner
+            .tokens
+            .extend(initrds.into_iter().map(Token::Initrd));
+
+        self
+    }
+
+    /// Build the `Entry`.
+    pub fn build(self) -> Entry {
+        self.inner
+    }
+
+    /// Build the `Entry`, checking that it can be written and read back.
+    ///
+    /// Fails with the error of `Entry::validate_id()` if the id is invalid, or with
+    /// `LibSDBootConfError::InvalidTokenValue` on the first token with an empty value or a value
+    /// spanning multiple lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::entry::EntryBuilder;
+    ///
+    /// assert!(EntryBuilder::new("5.12.0-aosc-main").title("AOSC OS").try_build().is_ok());
+    /// assert!(EntryBuilder::new("aosc/main").title("AOSC OS").try_build().is_err());
+    /// assert!(EntryBuilder::new("5.12.0-aosc-main").title("").try_build().is_err());
+    /// ```
+    pub fn try_build(self) -> Result<Entry, LibSDBootConfError> {
+        self.inner.validate_id()?;
+
+        for token in &self.inner.tokens {
+            check_token_value(token.key(), &token.value_string())?;
+        }
+
+        Ok(self.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("5.12.0-aosc-main")
+            .build();
+
+        println!("{:?}", &entry);
+    }
+
+    #[test]
+    fn test_devicetree() {
+        let content = "title AOSC OS\n\
+                       linux /vmlinuz-5.12.0-aosc-main\n\
+                       devicetree /dtbs/5.12.0-aosc-main/rockchip/rk3399-rockpro64.dtb\n";
+        let entry = Entry::from_str(content).unwrap();
+
+        assert_eq!(
+            entry,
+            EntryBuilder::new("")
+                .title("AOSC OS")
+                .linux("/vmlinuz-5.12.0-aosc-main")
+                .devicetree("/dtbs/5.12.0-aosc-main/rockchip/rk3399-rockpro64.dtb")
+                .build()
+        );
+        assert_eq!(entry.to_string(), content);
+    }
+
+    #[test]
+    fn test_devicetree_overlay() {
+        let content = "devicetree-overlay /overlays/b.dtbo /overlays/a.dtbo\n";
+        let entry = Entry::from_str(content).unwrap();
+
+        assert_eq!(
+            entry.tokens,
+            vec![Token::DeviceTreeOverlay(vec![
+                PathBuf::from("/overlays/b.dtbo"),
+                PathBuf::from("/overlays/a.dtbo"),
+            ])]
+        );
+        assert_eq!(entry.to_string(), content);
+        assert_eq!(
+            EntryBuilder::new("")
+                .devicetree_overlay([
+                    PathBuf::from("/overlays/b.dtbo"),
+                    PathBuf::from("/overlays/a.dtbo"),
+                ])
+                .build(),
+            entry
+        );
+    }
+
+    #[test]
+    fn test_architecture() {
+        for architecture in ["x64", "aa64", "loongarch64"] {
+            let content = format!("title AOSC OS\narchitecture {}\n", architecture);
+            let entry = Entry::from_str(&content).unwrap();
+
+            assert_eq!(
+                entry,
+                EntryBuilder::new("")
+                    .title("AOSC OS")
+                    .architecture(architecture)
+                    .build()
+            );
+            assert_eq!(entry.to_string(), content);
+        }
+    }
+
+    #[test]
+    fn test_sort_key() {
+        let content = "title AOSC OS\nsort-key aosc\n";
+        let entry = Entry::from_str(content).unwrap();
+
+        assert_eq!(
+            entry,
+            EntryBuilder::new("")
+                .title("AOSC OS")
+                .sort_key("aosc")
+                .build()
+        );
+        assert_eq!(entry.sort_key(), Some("aosc"));
+        assert_eq!(entry.to_string(), content);
+    }
+
+    #[test]
+    fn test_display() {
+        let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .devicetree_overlay([PathBuf::from("/a.dtbo"), PathBuf::from("/b.dtbo")])
+            .build();
+
+        assert_eq!(
+            format!("{}", entry),
+            "title AOSC OS\n\
+             linux /vmlinuz-5.12.0-aosc-main\n\
+             devicetree-overlay /a.dtbo /b.dtbo\n"
+        );
+        assert_eq!(format!("{}", entry.tokens[0]), "title AOSC OS\n");
+    }
+
+    #[test]
+    fn test_tab_separated() {
+        assert_eq!(
+            Entry::from_str("title\tAOSC OS\noptions\troot=/dev/sda1 rw\n").unwrap(),
+            Entry::from_str("title AOSC OS\noptions root=/dev/sda1 rw\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_error_line() {
+        let error = Entry::parse_strict("title AOSC OS\n# comment\nfoo bar\n").unwrap_err();
+
+        assert_eq!(error.to_string(), "invalid token 'foo' at line 3");
+
+        let error = Entry::from_str("title AOSC OS\nlinux\n").unwrap_err();
+
+        assert_eq!(error.to_string(), "missing value for 'linux' at line 2");
+    }
+
+    #[test]
+    fn test_set_title() {
+        let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+            .version("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .build();
+
+        entry.set_title("AOSC OS x86_64");
+
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Version("5.12.0-aosc-main".to_owned()),
+                Token::Title("AOSC OS x86_64".to_owned()),
+                Token::Linux(PathBuf::from("/vmlinuz-5.12.0-aosc-main")),
+            ]
+        );
+
+        entry.set_options("root=/dev/sda1 rw");
+
+        assert_eq!(
+            entry.tokens.last(),
+            Some(&Token::Options("root=/dev/sda1 rw".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .title("AOSC OS x86_64")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .options("root=/dev/sda1")
+            .options("rw")
+            .build();
+
+        assert_eq!(entry.title(), Some("AOSC OS"));
+        assert_eq!(entry.linux(), Some(Path::new("/vmlinuz-5.12.0-aosc-main")));
+        assert_eq!(entry.options(), Some("root=/dev/sda1"));
+        assert_eq!(entry.version(), None);
+        assert_eq!(entry.machine_id(), None);
+        assert_eq!(entry.efi(), None);
+
+        entry.set_version("5.12.0-aosc-main");
+        entry.set_linux("/vmlinuz-5.12.1-aosc-main");
+
+        assert_eq!(entry.version(), Some("5.12.0-aosc-main"));
+        assert_eq!(entry.linux(), Some(Path::new("/vmlinuz-5.12.1-aosc-main")));
+        assert_eq!(
+            entry.tokens[2],
+            Token::Linux(PathBuf::from("/vmlinuz-5.12.1-aosc-main"))
+        );
+        assert_eq!(
+            entry.tokens.last(),
+            Some(&Token::Version("5.12.0-aosc-main".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_set_token_removes_duplicates() {
+        let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+            .options("root=/dev/sda1")
+            .title("AOSC OS")
+            .options("rw")
+            .build();
+
+        entry.set_options("root=/dev/sda2 rw");
+
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Options("root=/dev/sda2 rw".to_owned()),
+                Token::Title("AOSC OS".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiple_initrds() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("5.12.0-aosc-main.conf");
+        let content = "title AOSC OS\n\
+                       linux /vmlinuz-5.12.0-aosc-main\n\
+                       initrd /intel-ucode.img\n\
+                       initrd /initramfs-5.12.0-aosc-main.img\n";
+        fs::write(&path, content).unwrap();
+
+        let entry = Entry::load(&path).unwrap();
+
+        assert_eq!(
+            entry,
+            EntryBuilder::new("5.12.0-aosc-main")
+                .title("AOSC OS")
+                .linux("/vmlinuz-5.12.0-aosc-main")
+                .initrds([
+                    PathBuf::from("/intel-ucode.img"),
+                    PathBuf::from("/initramfs-5.12.0-aosc-main.img"),
+                ])
+                .build()
+        );
+
+        entry.write(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(Entry::load(&path).unwrap().initrds().len(), 2);
+    }
+
+    #[test]
+    fn test_validate_paths() {
+        let esp = tempfile::tempdir().unwrap();
+        fs::create_dir(esp.path().join("EFI")).unwrap();
+        fs::write(esp.path().join("EFI/vmlinuz-5.12.0-aosc-main"), "").unwrap();
+        fs::write(esp.path().join("EFI/intel-ucode.img"), "").unwrap();
+
+        let valid = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .linux("/EFI/vmlinuz-5.12.0-aosc-main")
+            .initrd("EFI/intel-ucode.img")
+            .build();
+        let invalid = EntryBuilder::new("5.12.0-aosc-main")
+            .linux("/EFI/vmlinuz-5.12.0-aosc-main")
+            .initrd("/EFI/intel-ucode.img")
+            .initrd("/EFI/initramfs-5.12.0-aosc-main.img")
+            .devicetree("/EFI/rk3399-rockpro64.dtb")
+            .build();
+
+        assert_eq!(valid.validate_paths(esp.path()), Ok(()));
+        assert_eq!(
+            invalid.validate_paths(esp.path()),
+            Err(vec![
+                PathBuf::from("/EFI/initramfs-5.12.0-aosc-main.img"),
+                PathBuf::from("/EFI/rk3399-rockpro64.dtb"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_to_env() {
         let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS's \"main\" kernel $(rm -rf /)")
+            .options("root=/dev/sda1 rw")
+            .options("quiet")
+            .build();
+
+        assert_eq!(
+            entry.to_env(),
+            concat!(
+                "SDBOOT_ID='5.12.0-aosc-main'\n",
+                "SDBOOT_TITLE='AOSC OS'\\''s \"main\" kernel $(rm -rf /)'\n",
+                "SDBOOT_OPTIONS='root=/dev/sda1 rw quiet'\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_env_collisions() {
+        let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .title("AOSC OS x86_64")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .build();
+
+        for line in [
+            "id evil",
+            "I-D evil",
+            "lïnux /evil.efi",
+            "x-foo bar",
+            "x-foo baz",
+        ] {
+            entry.push_token(line.parse().unwrap());
+        }
+
+        assert_eq!(
+            entry.to_env(),
+            concat!(
+                "SDBOOT_ID='5.12.0-aosc-main'\n",
+                "SDBOOT_TITLE='AOSC OS x86_64'\n",
+                "SDBOOT_LINUX='/vmlinuz-5.12.0-aosc-main'\n",
+                "SDBOOT_X_ID='evil'\n",
+                "SDBOOT_X_I_D='evil'\n",
+                "SDBOOT_X_L_NUX='/evil.efi'\n",
+                "SDBOOT_X_X_FOO='bar baz'\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_dedup_singletons() {
+        let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .linux("/vmlinuz-5.11.0-aosc-main")
+            .initrd("/intel-ucode.img")
+            .options("root=/dev/sda1")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .initrd("/initramfs-5.12.0-aosc-main.img")
+            .options("rw")
+            .title("AOSC OS x86_64")
+            .build();
+
+        entry.dedup_singletons();
+
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Initrd(PathBuf::from("/intel-ucode.img")),
+                Token::Options("root=/dev/sda1".to_owned()),
+                Token::Linux(PathBuf::from("/vmlinuz-5.12.0-aosc-main")),
+                Token::Initrd(PathBuf::from("/initramfs-5.12.0-aosc-main.img")),
+                Token::Options("rw".to_owned()),
+                Token::Title("AOSC OS x86_64".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_options_with() {
+        let dedup = |policy| {
+            let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+                .title("5.12.0-aosc-main")
+                .options("root=/dev/sda1 quiet rw")
+                .options("quiet root=/dev/sda2")
+                .build();
+
+            entry.dedup_options_with(policy);
+
+            entry.tokens
+        };
+
+        assert_eq!(
+            dedup(DedupPolicy::default()),
+            vec![
+                Token::Title("5.12.0-aosc-main".to_owned()),
+                Token::Options("quiet rw".to_owned()),
+                Token::Options("root=/dev/sda2".to_owned()),
+            ]
+        );
+        assert_eq!(
+            dedup(DedupPolicy {
+                key_value: Precedence::First,
+                flags: Precedence::Last,
+            }),
+            vec![
+                Token::Title("5.12.0-aosc-main".to_owned()),
+                Token::Options("root=/dev/sda1 rw".to_owned()),
+                Token::Options("quiet".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_initrds() {
+        let mut entry = EntryBuilder::new("5.12.0-aosc-main")
             .title("5.12.0-aosc-main")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .initrd("/initramfs-5.11.0-aosc-main.img")
+            .options("root=/dev/sda1 rw")
             .build();
 
-        println!("{:?}", &entry);
+        let microcode = [
+            PathBuf::from("/amd-ucode.img"),
+            PathBuf::from("/intel-ucode.img"),
+        ];
+
+        entry.set_initrds(
+            microcode
+                .into_iter()
+                .chain([PathBuf::from("/initramfs-5.12.0-aosc-main.img")]),
+        );
+
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Title("5.12.0-aosc-main".to_owned()),
+                Token::Linux(PathBuf::from("/vmlinuz-5.12.0-aosc-main")),
+                Token::Initrd(PathBuf::from("/amd-ucode.img")),
+                Token::Initrd(PathBuf::from("/intel-ucode.img")),
+                Token::Initrd(PathBuf::from("/initramfs-5.12.0-aosc-main.img")),
+                Token::Options("root=/dev/sda1 rw".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_token() {
+        let content = "title AOSC OS\n\
+                       x-aosc-flavor main\n\
+                       linux /vmlinuz-5.12.0-aosc-main\n";
+        let entry = Entry::from_str(content).unwrap();
+
+        assert_eq!(
+            entry.tokens[1],
+            Token::Unknown {
+                key: "x-aosc-flavor".to_owned(),
+                value: "main".to_owned(),
+            }
+        );
+        assert_eq!(entry.to_string(), content);
+        assert!(entry.to_env().contains("SDBOOT_X_X_AOSC_FLAVOR='main'\n"));
+    }
+
+    #[test]
+    fn test_unknown_token_strict() {
+        let content = "title AOSC OS\nx-aosc-flavor main\n";
+
+        assert!(matches!(
+            Entry::parse_strict(content),
+            Err(LibSDBootConfError::InvalidTokenAt { token, line: 2 }) if token == "x-aosc-flavor"
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("5.12.0-aosc-main.conf");
+        fs::write(&path, content).unwrap();
+
+        assert!(Entry::load_strict(&path).is_err());
+        assert_eq!(Entry::load(&path).unwrap().tokens.len(), 2);
+    }
+
+    #[test]
+    fn test_crlf() {
+        let entry = Entry::from_str("title AOSC OS \r\nlinux /vmlinuz\r\n").unwrap();
+
+        assert_eq!(entry.title(), Some("AOSC OS"));
+        assert_eq!(entry.linux(), Some(Path::new("/vmlinuz")));
+    }
+
+    #[test]
+    fn test_trailing_newline() {
+        let content = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .initrd("/initramfs-5.12.0-aosc-main.img")
+            .options("root=/dev/sda1 rw")
+            .build()
+            .to_string();
+
+        assert!(content.ends_with('\n'));
+        assert!(!content.ends_with("\n\n"));
+        assert!(!content.contains("\n\n"));
+        assert_eq!(Entry::default().to_string(), "");
+    }
+
+    #[test]
+    fn test_add_remove_option() {
+        let mut entry = EntryBuilder::new("5.12.0-aosc-main")
+            .options("root=/dev/sda1  rw")
+            .title("AOSC OS")
+            .options("")
+            .build();
+
+        assert!(entry.add_option("quiet"));
+        assert!(!entry.add_option("rw"));
+        assert_eq!(entry.options_list(), vec!["root=/dev/sda1", "rw", "quiet"]);
+        assert_eq!(
+            entry.tokens[0],
+            Token::Options("root=/dev/sda1  rw".to_owned())
+        );
+        assert_eq!(entry.tokens[2], Token::Options("quiet".to_owned()));
+
+        assert!(entry.remove_option("quiet"));
+        assert!(!entry.remove_option("quiet"));
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Options("root=/dev/sda1  rw".to_owned()),
+                Token::Title("AOSC OS".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_keys() {
+        let entry = Entry::from_str("Title AOSC OS\nLINUX /vmlinuz\n").unwrap();
+
+        assert_eq!(entry.title(), Some("AOSC OS"));
+        assert_eq!(entry.to_string(), "title AOSC OS\nlinux /vmlinuz\n");
+    }
+
+    #[test]
+    fn test_options_from() {
+        let params = vec!["root=/dev/sda1".to_owned(), "rw".to_owned()];
+        let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .options_from(&params)
+            .options_from(["quiet"])
+            .build();
+
+        assert_eq!(
+            entry.to_string(),
+            "options root=/dev/sda1 rw\noptions quiet\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_token_order() {
+        let mut entry = Entry::from_str(
+            "initrd /intel-ucode.img\n\
+             x-aosc-flavor main\n\
+             linux /vmlinuz-5.12.0-aosc-main\n\
+             options quiet\n\
+             initrd /initramfs-5.12.0-aosc-main.img\n\
+             version 5.12.0-aosc-main\n\
+             title AOSC OS\n\
+             options root=/dev/sda1\n",
+        )
+        .unwrap();
+
+        entry.normalize_token_order();
+
+        assert_eq!(
+            entry.to_string(),
+            "title AOSC OS\n\
+             version 5.12.0-aosc-main\n\
+             options quiet\n\
+             options root=/dev/sda1\n\
+             linux /vmlinuz-5.12.0-aosc-main\n\
+             initrd /intel-ucode.img\n\
+             initrd /initramfs-5.12.0-aosc-main.img\n\
+             x-aosc-flavor main\n"
+        );
+    }
+
+    #[test]
+    fn test_key_only_title() {
+        assert!(matches!(
+            Entry::from_str("linux /vmlinuz\ntitle\n"),
+            Err(LibSDBootConfError::MissingValue { key, line: 2 }) if key == "title"
+        ));
+    }
+
+    #[test]
+    fn test_kind() {
+        let linux = EntryBuilder::new("linux").linux("/vmlinuz").build();
+        let efi = EntryBuilder::new("uki").efi("/EFI/Linux/aosc.efi").build();
+        let both = EntryBuilder::new("both")
+            .efi("/EFI/Linux/aosc.efi")
+            .linux("/vmlinuz")
+            .build();
+        let unknown = EntryBuilder::new("unknown").title("AOSC OS").build();
+
+        assert_eq!(linux.kind(), EntryKind::Linux);
+        assert_eq!(efi.kind(), EntryKind::Efi);
+        assert_eq!(both.kind(), EntryKind::Linux);
+        assert_eq!(unknown.kind(), EntryKind::Unknown);
+    }
+
+    #[test]
+    fn test_validate_id() {
+        assert!(Entry::new("5.12.0-aosc-main", Vec::new())
+            .validate_id()
+            .is_ok());
+
+        for id in [
+            "",
+            "aosc/main",
+            "aosc\\main",
+            "AOSC OS",
+            "aosc\tmain",
+            "aosc\0main",
+        ] {
+            assert!(matches!(
+                Entry::new(id, Vec::new()).validate_id(),
+                Err(LibSDBootConfError::InvalidEntryId { id: invalid, .. }) if invalid == id
+            ));
+        }
+
+        assert_eq!(
+            Entry::new("AOSC OS", Vec::new())
+                .validate_id()
+                .unwrap_err()
+                .to_string(),
+            "invalid entry id 'AOSC OS': id contains whitespace"
+        );
+    }
+
+    #[test]
+    fn test_token_list_helpers() {
+        let mut entry = [
+            Token::Title("AOSC OS".to_owned()),
+            Token::Initrd(PathBuf::from("/intel-ucode.img")),
+            Token::Linux(PathBuf::from("/vmlinuz")),
+        ]
+        .into_iter()
+        .collect::<Entry>();
+
+        entry.push_token(Token::Initrd(PathBuf::from("/initramfs.img")));
+
+        assert_eq!(entry.id, "");
+        assert_eq!(
+            entry.tokens_of_kind(&Token::Initrd(PathBuf::new())),
+            vec![
+                &Token::Initrd(PathBuf::from("/intel-ucode.img")),
+                &Token::Initrd(PathBuf::from("/initramfs.img")),
+            ]
+        );
+
+        entry.remove_tokens_matching(|token| matches!(token, Token::Initrd(_)));
+
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Title("AOSC OS".to_owned()),
+                Token::Linux(PathBuf::from("/vmlinuz")),
+            ]
+        );
+        assert!(entry.with_tokens(Vec::new()).tokens.is_empty());
+    }
+
+    #[test]
+    fn test_write_with_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("5.12.0-aosc-main.conf");
+        let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .build();
+
+        entry
+            .write_with_header(&path, "Generated by aosc-installer\n# do not edit")
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Generated by aosc-installer\n# do not edit\ntitle AOSC OS\n"
+        );
+        assert_eq!(Entry::load(&path).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_clone() {
+        let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .build();
+        let mut cloned = entry.clone();
+
+        cloned.set_title("AOSC OS x86_64");
+
+        assert_eq!(entry.title(), Some("AOSC OS"));
+        assert_eq!(cloned.title(), Some("AOSC OS x86_64"));
+        assert_eq!(cloned.tokens[1], entry.tokens[1]);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let entry = Entry::from_reader(std::io::Cursor::new(
+            b"title AOSC OS\nlinux /vmlinuz\n".to_vec(),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            entry,
+            EntryBuilder::new("")
+                .title("AOSC OS")
+                .linux("/vmlinuz")
+                .build()
+        );
+    }
+
+    #[test]
+    fn test_parse_with_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("5.12.0-aosc-main.conf");
+        fs::write(
+            &path,
+            "title AOSC OS\n\
+             x-aosc-flavor main\n\
+             initrd /intel-ucode.img\n\
+             initrd /initramfs.img\n\
+             linux\n\
+             Title AOSC OS x86_64\n",
+        )
+        .unwrap();
+
+        let report = Entry::load_with_report(&path).unwrap();
+
+        assert_eq!(report.value.id, "5.12.0-aosc-main");
+        assert_eq!(report.value.tokens.len(), 5);
+        assert_eq!(
+            report.warnings,
+            vec![
+                ParseWarning::UnknownKey {
+                    key: "x-aosc-flavor".to_owned(),
+                    line: 2,
+                },
+                ParseWarning::InvalidValue {
+                    line: 5,
+                    message: "missing value for 'linux' at line 5".to_owned(),
+                },
+                ParseWarning::DuplicateKey {
+                    key: "Title".to_owned(),
+                    line: 6,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leading_whitespace() {
+        let entry = Entry::from_str("  # indented comment\n  title AOSC OS\n").unwrap();
+
+        assert_eq!(entry.tokens, vec![Token::Title("AOSC OS".to_owned())]);
+    }
+
+    #[test]
+    fn test_update_kernel() {
+        let mut entry = EntryBuilder::new("aosc")
+            .title("AOSC OS")
+            .linux("/vmlinuz-5.11.0-aosc-main")
+            .initrd("/intel-ucode.img")
+            .initrd("/initramfs-5.11.0-aosc-main.img")
+            .options("root=/dev/sda1 rw")
+            .build();
+
+        entry.set_linux("/vmlinuz-5.12.0-aosc-main");
+        entry.set_initrds([PathBuf::from("/initramfs-5.12.0-aosc-main.img")]);
+
+        assert_eq!(
+            entry.to_string(),
+            "title AOSC OS\n\
+             linux /vmlinuz-5.12.0-aosc-main\n\
+             initrd /initramfs-5.12.0-aosc-main.img\n\
+             options root=/dev/sda1 rw\n"
+        );
+    }
+
+    #[test]
+    fn test_bom() {
+        let entry = Entry::from_str("\u{feff}title AOSC OS\n").unwrap();
+
+        assert_eq!(entry.tokens, vec![Token::Title("AOSC OS".to_owned())]);
+    }
+
+    #[test]
+    fn test_title_from_os_release() {
+        let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title_from_os_release("AOSC OS x86_64", "5.12.0-aosc-main")
+            .build();
+
+        assert_eq!(
+            entry.to_string(),
+            "title AOSC OS x86_64 (5.12.0-aosc-main)\n"
+        );
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let entry = Entry::new("a", vec![Token::Title("AOSC OS".to_owned())]);
+        let same = Entry::new("b", vec![Token::Title("AOSC OS".to_owned())]);
+        let other = Entry::new("a", vec![Token::Title("AOSC OS (fallback)".to_owned())]);
+
+        assert_eq!(entry.content_hash(), same.content_hash());
+        assert_ne!(entry.content_hash(), other.content_hash());
+    }
+
+    #[test]
+    fn test_lint() {
+        let entry = Entry::new(
+            "a",
+            vec![
+                Token::Linux(PathBuf::from("vmlinuz-5.12.0-aosc-main")),
+                Token::Initrd(PathBuf::from("\\initramfs-5.12.0-aosc-main.img")),
+                Token::Initrd(PathBuf::from("/amd-ucode.img")),
+                Token::Efi(PathBuf::from("EFI\\Linux\\aosc.efi")),
+            ],
+        );
+
+        assert_eq!(
+            entry.lint(),
+            vec![
+                EntryLint::RelativePath {
+                    key: "linux",
+                    path: PathBuf::from("vmlinuz-5.12.0-aosc-main"),
+                },
+                EntryLint::BackslashPath {
+                    key: "initrd",
+                    path: PathBuf::from("\\initramfs-5.12.0-aosc-main.img"),
+                },
+                EntryLint::BackslashPath {
+                    key: "efi",
+                    path: PathBuf::from("EFI\\Linux\\aosc.efi"),
+                },
+                EntryLint::RelativePath {
+                    key: "efi",
+                    path: PathBuf::from("EFI\\Linux\\aosc.efi"),
+                },
+            ]
+        );
+        assert!(Entry::from_str("linux /vmlinuz\n")
+            .unwrap()
+            .lint()
+            .is_empty());
+        assert!(Entry::from_str("linux vmlinuz\n").is_ok());
+    }
+
+    #[test]
+    fn test_builder_opt() {
+        let machine_id: Option<&str> = None;
+        let entry = EntryBuilder::new("a")
+            .title_opt(Some("AOSC OS"))
+            .machine_id_opt(machine_id)
+            .linux_opt(Some("/vmlinuz"))
+            .initrd_opt(None::<PathBuf>)
+            .build();
+
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Title("AOSC OS".to_owned()),
+                Token::Linux(PathBuf::from("/vmlinuz")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_key_value_string() {
+        let tokens = [
+            (Token::Title("AOSC OS".to_owned()), "title", "AOSC OS"),
+            (Token::SortKey("aosc".to_owned()), "sort-key", "aosc"),
+            (Token::Linux(PathBuf::from("/vmlinuz")), "linux", "/vmlinuz"),
+            (
+                Token::from_str("X-Foo bar baz").unwrap(),
+                "x-foo",
+                "bar baz",
+            ),
+        ];
+
+        for (token, key, value) in tokens {
+            assert_eq!(token.key(), key);
+            assert_eq!(token.value_string(), value);
+            assert_eq!(token.to_string(), format!("{} {}\n", key, value));
+        }
+    }
+
+    #[test]
+    fn test_effective_options() {
+        let entry = Entry::from_str(
+            "title AOSC OS\noptions root=/dev/sda1 rw\nlinux /vmlinuz\noptions quiet splash\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entry.effective_options().as_deref(),
+            Some("root=/dev/sda1 rw quiet splash")
+        );
+        assert_eq!(
+            Entry::from_str("title AOSC OS\n")
+                .unwrap()
+                .effective_options(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_options_continuation() {
+        let entry = Entry::from_str(
+            "title AOSC OS\noptions root=/dev/sda1 rw \\\n    quiet splash\nlinux /vmlinuz\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entry.tokens,
+            vec![
+                Token::Title("AOSC OS".to_owned()),
+                Token::Options("root=/dev/sda1 rw quiet splash".to_owned()),
+                Token::Linux(PathBuf::from("/vmlinuz")),
+            ]
+        );
+        assert!(matches!(
+            Entry::from_str("options quiet \\\nlinux\n"),
+            Ok(entry) if entry.options() == Some("quiet linux")
+        ));
+        assert_eq!(
+            Entry::from_str("title AOSC OS\noptions quiet splash \\\n")
+                .unwrap()
+                .effective_options()
+                .as_deref(),
+            Some("quiet splash")
+        );
+        assert!(matches!(
+            Entry::from_str("options quiet\nlinux\n"),
+            Err(LibSDBootConfError::MissingValue { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_map_round_trip() {
+        let entry = EntryBuilder::new("5.12.0-aosc-main")
+            .title("AOSC OS")
+            .options("root=/dev/sda1 rw")
+            .linux("/vmlinuz-5.12.0-aosc-main")
+            .initrd("/intel-ucode.img")
+            .initrd("/initramfs-5.12.0-aosc-main.img")
+            .devicetree_overlay([PathBuf::from("/a.dtbo"), PathBuf::from("/b.dtbo")])
+            .build();
+        let map = entry.to_map();
+
+        assert_eq!(
+            map["initrd"],
+            vec!["/intel-ucode.img", "/initramfs-5.12.0-aosc-main.img"]
+        );
+        assert_eq!(map["devicetree-overlay"], vec!["/a.dtbo /b.dtbo"]);
+        assert_eq!(Entry::from_map("5.12.0-aosc-main", map).unwrap(), entry);
+        assert!(matches!(
+            Entry::from_map(
+                "a",
+                BTreeMap::from([("title".to_owned(), vec![String::new()])])
+            ),
+            Err(LibSDBootConfError::InvalidTokenValue { key, reason: "empty value" }) if key == "title"
+        ));
+        assert!(matches!(
+            Entry::from_map(
+                "a",
+                BTreeMap::from([("options".to_owned(), vec!["quiet\nrw".to_owned()])])
+            ),
+            Err(LibSDBootConfError::InvalidTokenValue {
+                reason: "value spans multiple lines",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Entry::from_map(
+                "a",
+                BTreeMap::from([("sort key".to_owned(), vec!["aosc".to_owned()])])
+            ),
+            Err(LibSDBootConfError::InvalidTokenValue {
+                reason: "invalid key",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_build() {
+        assert_eq!(
+            EntryBuilder::new("5.12.0-aosc-main")
+                .title("AOSC OS")
+                .linux("/vmlinuz")
+                .try_build()
+                .unwrap(),
+            EntryBuilder::new("5.12.0-aosc-main")
+                .title("AOSC OS")
+                .linux("/vmlinuz")
+                .build()
+        );
+        assert!(matches!(
+            EntryBuilder::new("aosc main").title("AOSC OS").try_build(),
+            Err(LibSDBootConfError::InvalidEntryId { .. })
+        ));
+        assert!(matches!(
+            EntryBuilder::new("").try_build(),
+            Err(LibSDBootConfError::InvalidEntryId { .. })
+        ));
+        assert!(matches!(
+            EntryBuilder::new("a").options("quiet\nrw").try_build(),
+            Err(LibSDBootConfError::InvalidTokenValue { key, reason: "value spans multiple lines" })
+                if key == "options"
+        ));
+        assert!(matches!(
+            EntryBuilder::new("a").linux("").try_build(),
+            Err(LibSDBootConfError::InvalidTokenValue { key, reason: "empty value" })
+                if key == "linux"
+        ));
+    }
+
+    #[test]
+    fn test_reorder_initrds() {
+        let ucode = |path: &PathBuf| {
+            if path.to_string_lossy().contains("ucode") {
+                0
+            } else {
+                1
+            }
+        };
+
+        for initrds in [
+            ["/initramfs.img", "/intel-ucode.img", "/amd-ucode.img"],
+            ["/intel-ucode.img", "/initramfs.img", "/amd-ucode.img"],
+        ] {
+            let mut entry = EntryBuilder::new("a")
+                .title("AOSC OS")
+                .initrd(initrds[0])
+                .linux("/vmlinuz")
+                .initrd(initrds[1])
+                .initrd(initrds[2])
+                .build();
+
+            entry.reorder_initrds(ucode);
+
+            assert_eq!(
+                entry.tokens,
+                vec![
+                    Token::Title("AOSC OS".to_owned()),
+                    Token::Initrd(PathBuf::from("/intel-ucode.img")),
+                    Token::Linux(PathBuf::from("/vmlinuz")),
+                    Token::Initrd(PathBuf::from("/amd-ucode.img")),
+                    Token::Initrd(PathBuf::from("/initramfs.img")),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_named() {
+        let path = Path::new("/var/lib/staging/5.12.0-aosc-main.conf");
+
+        assert_eq!(
+            Entry::parse_named("title AOSC OS\n", path).unwrap(),
+            Entry::from_str("title AOSC OS\n").unwrap()
+        );
+        assert!(matches!(
+            Entry::parse_named("title AOSC OS\nlinux\n", path),
+            Err(LibSDBootConfError::ParseErrorIn { path: error_path, source })
+                if error_path == path
+                    && matches!(*source, LibSDBootConfError::MissingValue { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_commented_entry() {
+        let mut entry = CommentedEntry::from(
+            EntryBuilder::new("5.12.0-aosc-main")
+                .title("AOSC OS")
+                .linux("/vmlinuz-5.12.0-aosc-main")
+                .options("quiet")
+                .build(),
+        );
+
+        entry.tokens[1].comment = Some("fallback kernel".to_owned());
+        entry.push_commented_token(
+            Token::Initrd(PathBuf::from("/initramfs-5.12.0-aosc-main.img")),
+            "generated by aosc-installer\n# do not edit",
+        );
+
+        assert_eq!(
+            entry.to_string(),
+            "title AOSC OS\n\
+             # fallback kernel\n\
+             linux /vmlinuz-5.12.0-aosc-main\n\
+             options quiet\n\
+             # generated by aosc-installer\n\
+             # do not edit\n\
+             initrd /initramfs-5.12.0-aosc-main.img\n"
+        );
+
+        // Comments stay with their tokens when other tokens are removed or reordered.
+        entry
+            .tokens
+            .retain(|token| !matches!(token.token, Token::Title(_)));
+        entry
+            .tokens
+            .sort_by_key(|token| token.token.canonical_position());
+
+        assert_eq!(
+            entry.to_string(),
+            "options quiet\n\
+             # fallback kernel\n\
+             linux /vmlinuz-5.12.0-aosc-main\n\
+             # generated by aosc-installer\n\
+             # do not edit\n\
+             initrd /initramfs-5.12.0-aosc-main.img\n"
+        );
+
+        let parsed = Entry::from_str(&entry.to_string()).unwrap();
+
+        assert_eq!(
+            Entry {
+                id: entry.id.clone(),
+                ..parsed
+            },
+            Entry::from(entry)
+        );
     }
 }
@@ -45,10 +45,10 @@
 //! # Create a new systemd-boot menu entry
 //!
 //! ```no_run
-//! use libsdbootconf::entry::{Entry, EntryBuilder, Token};
+//! use libsdbootconf::entry::{Entry, Token};
 //! use std::path::PathBuf;
 //!
-//! let entry = EntryBuilder::new("5.12.0-aosc-main")
+//! let entry = Entry::builder("5.12.0-aosc-main")
 //!     .title("AOSC OS x86_64 (5.12.0-aosc-main)")
 //!     .linux("/EFI/linux/vmlinux-5.12.0-aosc-main")
 //!     .initrd("/EFI/linux/initramfs-5.12.0-aosc-main.img")
@@ -70,39 +70,111 @@
 //! ```
 
 use std::{
-    fs,
-    path::{Path, PathBuf},
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, io, mem,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
 };
 use thiserror::Error;
 
+pub mod audit;
 pub mod config;
+pub mod diff;
 pub mod entry;
 mod macros;
+mod parser;
+pub mod report;
+pub mod util;
 
-use crate::macros::generate_builder_method;
-pub use config::{Config, ConfigBuilder};
+use crate::{
+    audit::AuditHook,
+    macros::{generate_builder_method, generate_token_method},
+};
+pub use audit::{AuditAction, AuditEvent};
+pub use config::{Config, ConfigBuilder, ConsoleMode, RandomSeedMode, SecureBootEnroll, Timeout};
+pub use diff::ConfDiff;
+use diff::ConfigChange;
+use entry::EntryKind;
 pub use entry::{Entry, EntryBuilder, Token};
+pub use report::{ConfSummary, Report};
+use report::{ConfigIssue, DefaultHealth, EntryIssue};
 
+/// Errors returned by this library.
+///
+/// New variants may be added in future releases, so matches on this enum need a wildcard arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum LibSDBootConfError {
     #[error("invalid configuration")]
     ConfigParseError,
+    #[error("invalid configuration at line {line}: {content}")]
+    ConfigParseErrorAt { line: usize, content: String },
     #[error("invalid entry")]
     EntryParseError,
+    #[error("invalid entry at line {line}: {content}")]
+    EntryParseErrorAt { line: usize, content: String },
     #[error("invalid entry filename {0}")]
     InvalidEntryFilename(PathBuf),
+    #[error("more than one entry would be written to {0}")]
+    DuplicateEntryFilename(PathBuf),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
     #[error("invalid token {0}")]
     InvalidToken(String),
+    #[error("invalid value for '{key}': {reason}")]
+    InvalidTokenValue { key: String, reason: &'static str },
+    #[error("invalid token '{token}' at line {line}")]
+    InvalidTokenAt { token: String, line: usize },
+    #[error("{0} does not parse back to the same content")]
+    RoundTripMismatch(PathBuf),
+    #[error("entry {0} not found")]
+    EntryNotFound(String),
+    #[error("missing value for '{key}' at line {line}")]
+    MissingValue { key: String, line: usize },
+    #[error("invalid entry id '{id}': {reason}")]
+    InvalidEntryId { id: String, reason: &'static str },
+    #[error("failed to load entry {path}: {source}")]
+    EntryLoadError {
+        path: PathBuf,
+        source: Box<LibSDBootConfError>,
+    },
+    #[error("{0} is not valid UTF-8")]
+    InvalidEncoding(PathBuf),
+    #[error("failed to parse {path}: {source}")]
+    ParseErrorIn {
+        path: PathBuf,
+        source: Box<LibSDBootConfError>,
+    },
+    #[error("no loader directory found under {0}")]
+    LoaderNotFound(PathBuf),
+}
+
+/// Files in the working directory that are not modeled by this library.
+const AUX_FILES: &[&str] = &["entries.srel"];
+
+/// Locations of the loader directory relative to a mountpoint, in the order they are searched.
+const LOADER_DIRS: &[&str] = &["loader", "efi/loader", "boot/loader", "boot/efi/loader"];
+
+/// How `SystemdBootConf::ensure_default_valid()` repairs a dangling default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultRepair {
+    /// Unset the default.
+    Clear,
+    /// Set the default to the first entry in menu order, see
+    /// `SystemdBootConf::entries_in_menu_order()`.
+    Repoint,
 }
 
 /// An abstraction over the basic structure of systemd-boot configurations.
 #[derive(Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SystemdBootConf {
     pub working_dir: PathBuf,
     pub config: Config,
     pub entries: Vec<Entry>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    audit_hook: Option<AuditHook>,
 }
 
 impl SystemdBootConf {
@@ -128,9 +200,28 @@ impl SystemdBootConf {
             working_dir: working_dir.into(),
             config: config.into(),
             entries: entries.into(),
+            audit_hook: None,
         }
     }
 
+    /// Create a `SystemdBootConfBuilder` with a working directory, same as
+    /// `SystemdBootConfBuilder::new()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::builder("/efi/loader")
+    ///     .entry(Entry::builder("5.12.0-aosc-main").build())
+    ///     .build();
+    ///
+    /// assert_eq!(systemd_boot_conf.entries.len(), 1);
+    /// ```
+    pub fn builder<P: Into<PathBuf>>(working_dir: P) -> SystemdBootConfBuilder {
+        SystemdBootConfBuilder::new(working_dir)
+    }
+
     /// Initialize a new `SystemdBootConf` with a working directory.
     ///
     /// # Examples
@@ -166,8 +257,68 @@ impl SystemdBootConf {
         Ok(systemd_boot_conf)
     }
 
+    /// Find and read a systemd-boot installation under a mountpoint.
+    ///
+    /// `esp_mount` is usually the mountpoint of the ESP, in which case `<esp_mount>/loader` is
+    /// used. The `efi`, `boot` and `boot/efi` subdirectories are searched as well, so the root
+    /// of the system works too. A loader directory containing `loader.conf` is preferred over
+    /// one without it. Returns `LibSDBootConfError::LoaderNotFound` if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    /// use std::path::Path;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::discover(Path::new("/efi")).unwrap();
+    ///
+    /// assert_eq!(systemd_boot_conf.working_dir, Path::new("/efi/loader"));
+    /// ```
+    pub fn discover(esp_mount: &Path) -> Result<Self, LibSDBootConfError> {
+        let candidates = LOADER_DIRS
+            .iter()
+            .map(|dir| esp_mount.join(dir))
+            .collect::<Vec<_>>();
+        let working_dir = candidates
+            .iter()
+            .find(|dir| dir.join("loader.conf").is_file())
+            .or_else(|| candidates.iter().find(|dir| dir.is_dir()))
+            .ok_or_else(|| LibSDBootConfError::LoaderNotFound(esp_mount.to_owned()))?;
+
+        Self::load(working_dir)
+    }
+
+    /// Read only `loader.conf` from an existing systemd-boot installation, without scanning the
+    /// `entries` directory.
+    ///
+    /// The entries are left empty, so `write_entries` on the returned instance writes nothing and
+    /// leaves the existing entry files untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::{SystemdBootConf, Timeout};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::load_config_only("/efi/loader").unwrap();
+    ///
+    /// systemd_boot_conf.config.timeout = Some(Timeout::Seconds(3));
+    /// systemd_boot_conf.write_config().unwrap();
+    /// ```
+    pub fn load_config_only<P: AsRef<Path>>(working_dir: P) -> Result<Self, LibSDBootConfError> {
+        let mut systemd_boot_conf = Self::init(working_dir.as_ref());
+
+        systemd_boot_conf.config = Config::load(working_dir.as_ref().join("loader.conf"))?;
+
+        Ok(systemd_boot_conf)
+    }
+
     /// Read from the current systemd-boot working directory.
     ///
+    /// A missing `loader.conf` is read as `Config::default()`, and a missing `entries` directory,
+    /// as found on a freshly installed ESP, is read as no entries. Other I/O errors, such as
+    /// insufficient permissions, are still returned. Only `.conf` files directly under `entries`
+    /// are read, other files and subdirectories are skipped.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -178,12 +329,24 @@ impl SystemdBootConf {
     /// systemd_boot_conf.load_current().unwrap();
     /// ```
     pub fn load_current(&mut self) -> Result<(), LibSDBootConfError> {
-        let config = Config::load(self.working_dir.join("loader.conf"))?;
+        let config = match Config::load(self.working_dir.join("loader.conf")) {
+            Ok(config) => config,
+            Err(LibSDBootConfError::IOError(error)) if error.kind() == io::ErrorKind::NotFound => {
+                Config::default()
+            }
+            Err(error) => return Err(error),
+        };
         let mut entries = Vec::new();
+        let files = match fs::read_dir(self.working_dir.join("entries")) {
+            Ok(files) => Some(files),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
 
-        for file in fs::read_dir(self.working_dir.join("entries"))? {
+        for file in files.into_iter().flatten() {
             let path = file?.path();
-            if path.is_file() {
+
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "conf") {
                 let entry = Entry::load(&path)?;
                 entries.push(entry);
             }
@@ -195,97 +358,2413 @@ impl SystemdBootConf {
         Ok(())
     }
 
-    /// Write systemd-boot configuration file to the system.
+    /// Load entries from the given files and append them to the entries, e.g. from a staging area
+    /// outside the `entries` directory.
+    ///
+    /// Returns `LibSDBootConfError::EntryLoadError` with the path of the first file that fails to
+    /// load, in which case no entry is appended.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// use libsdbootconf::SystemdBootConf;
+    /// use std::path::PathBuf;
     ///
-    /// let systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    /// let mut systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
     ///
-    /// systemd_boot_conf.write_config().unwrap();
+    /// systemd_boot_conf
+    ///     .load_entries_from([PathBuf::from("/var/lib/staging/5.12.0-aosc-main.conf")])
+    ///     .unwrap();
     /// ```
-    pub fn write_config(&self) -> Result<(), LibSDBootConfError> {
-        self.config.write(self.working_dir.join("loader.conf"))?;
+    pub fn load_entries_from<I: IntoIterator<Item = PathBuf>>(
+        &mut self,
+        paths: I,
+    ) -> Result<(), LibSDBootConfError> {
+        let entries = paths
+            .into_iter()
+            .map(|path| {
+                Entry::load(&path).map_err(|error| LibSDBootConfError::EntryLoadError {
+                    path,
+                    source: Box::new(error),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.entries.extend(entries);
 
         Ok(())
     }
 
-    /// Write all entries to the system.
+    /// Discard the in-memory configuration and entries, and read them again from the working
+    /// directory.
+    ///
+    /// This is the same as `load_current`. The working directory and the audit hook are kept.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// use libsdbootconf::SystemdBootConf;
     ///
-    /// let systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    /// let mut systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
     ///
-    /// systemd_boot_conf.write_entries().unwrap();
+    /// systemd_boot_conf.config.timeout = None;
+    /// systemd_boot_conf.reload().unwrap();
     /// ```
-    pub fn write_entries(&self) -> Result<(), LibSDBootConfError> {
-        for entry in self.entries.iter() {
-            entry.write(
-                self.working_dir
-                    .join("entries")
-                    .join(format!("{}.conf", entry.id)),
-            )?;
+    pub fn reload(&mut self) -> Result<(), LibSDBootConfError> {
+        self.load_current()
+    }
+
+    /// Read from an in-memory representation of a systemd-boot working directory.
+    ///
+    /// The keys of `files` are paths relative to the working directory, such as `loader.conf` and
+    /// `entries/5.12.0-aosc-main.conf`, and the values are the contents of the files. Like `load`,
+    /// only `.conf` files directly under `entries` are read as entries, and `loader.conf` is
+    /// required. Entries are sorted by their paths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::SystemdBootConf;
+    /// use std::{collections::HashMap, path::PathBuf};
+    ///
+    /// let files = HashMap::from([
+    ///     (PathBuf::from("loader.conf"), "timeout 5\n".to_owned()),
+    ///     (
+    ///         PathBuf::from("entries/5.12.0-aosc-main.conf"),
+    ///         "title AOSC OS\n".to_owned(),
+    ///     ),
+    /// ]);
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::from_map("/efi/loader", files).unwrap();
+    ///
+    /// assert_eq!(systemd_boot_conf.entries[0].id, "5.12.0-aosc-main");
+    /// ```
+    pub fn from_map<P: Into<PathBuf>>(
+        working_dir: P,
+        files: HashMap<PathBuf, String>,
+    ) -> Result<Self, LibSDBootConfError> {
+        let mut systemd_boot_conf = Self::init(working_dir);
+        let config = files.get(Path::new("loader.conf")).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{} not found",
+                    systemd_boot_conf.working_dir.join("loader.conf").display()
+                ),
+            )
+        })?;
+        let mut entries = files
+            .iter()
+            .filter(|(path, _)| {
+                path.parent() == Some(Path::new("entries"))
+                    && path.extension().is_some_and(|ext| ext == "conf")
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|(path, _)| *path);
+
+        systemd_boot_conf.config = Config::from_str(config)?;
+
+        for (path, content) in entries {
+            let mut entry = Entry::from_str(content)?;
+
+            entry.id = Entry::id_from_path(path)?.to_owned();
+            systemd_boot_conf.entries.push(entry);
         }
 
-        Ok(())
+        Ok(systemd_boot_conf)
     }
 
-    /// Write all configurations and entries to the system.
+    /// Read the auxiliary files in the working directory that are not modeled by this library,
+    /// such as `entries.srel`.
+    ///
+    /// Returns the path and the content of every known auxiliary file that exists. Content that is
+    /// not valid UTF-8 is converted lossily, so such a file is still reported. Files that cannot be
+    /// read at all, e.g. for lack of permission, are skipped. These files are never modified or
+    /// deleted by the write operations.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// use libsdbootconf::SystemdBootConf;
     ///
-    /// let systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    /// let systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
     ///
-    /// systemd_boot_conf.write_all().unwrap();
+    /// for (path, content) in systemd_boot_conf.aux_files() {
+    ///     println!("{}: {}", path.display(), content);
+    /// }
     /// ```
-    pub fn write_all(&self) -> Result<(), LibSDBootConfError> {
-        self.write_config()?;
-        self.write_entries()?;
+    pub fn aux_files(&self) -> Vec<(PathBuf, String)> {
+        AUX_FILES
+            .iter()
+            .map(|name| self.working_dir.join(name))
+            .filter_map(|path| {
+                fs::read(&path)
+                    .ok()
+                    .map(|content| (path, String::from_utf8_lossy(&content).into_owned()))
+            })
+            .collect()
+    }
 
-        Ok(())
+    /// Add an `Entry`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+    ///
+    /// assert_eq!(systemd_boot_conf.entries.len(), 1);
+    /// ```
+    pub fn add_entry(&mut self, entry: Entry) {
+        self.entries.push(entry);
     }
-}
 
-/// Builder for `SystemdBootConf`.
-#[derive(Default, Debug)]
-pub struct SystemdBootConfBuilder {
-    inner: SystemdBootConf,
-}
+    /// Remove the `Entry` with the given id, ignoring a trailing `.conf`, and return it.
+    ///
+    /// Only the in-memory entry is removed, use `remove_entry_file` to delete its file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+    ///
+    /// assert!(systemd_boot_conf.remove_entry("5.12.0-aosc-main.conf").is_some());
+    /// assert!(systemd_boot_conf.entries.is_empty());
+    /// ```
+    pub fn remove_entry(&mut self, id: &str) -> Option<Entry> {
+        let position = self.entries.iter().position(|entry| entry.has_id(id))?;
 
-impl SystemdBootConfBuilder {
-    /// Create an empty `SystemdBootConfBuilder` with a working directory.
-    pub fn new<P: Into<PathBuf>>(working_dir: P) -> Self {
-        Self {
-            inner: SystemdBootConf::init(working_dir),
+        Some(self.entries.remove(position))
+    }
+
+    /// Remove entries sharing an id with a later entry, ignoring a trailing `.conf`.
+    ///
+    /// The last entry of each id is kept, so entries loaded later, e.g. with `load_entries_from`
+    /// after `load_current`, replace the ones loaded earlier. The remaining entries keep their
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::builder("5.12.0-aosc-main").title("old").build());
+    /// systemd_boot_conf.add_entry(Entry::builder("5.12.0-aosc-main").title("new").build());
+    /// systemd_boot_conf.dedup_entries();
+    ///
+    /// assert_eq!(systemd_boot_conf.entries.len(), 1);
+    /// assert_eq!(systemd_boot_conf.entries[0].title(), Some("new"));
+    /// ```
+    pub fn dedup_entries(&mut self) {
+        let mut seen = BTreeSet::new();
+        let mut entries = mem::take(&mut self.entries)
+            .into_iter()
+            .rev()
+            .filter(|entry| seen.insert(entry.filename()))
+            .collect::<Vec<_>>();
+
+        entries.reverse();
+        self.entries = entries;
+    }
+
+    /// Get the `Entry` with the given id, ignoring a trailing `.conf` on both sides.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+    ///
+    /// assert!(systemd_boot_conf.get_entry("5.12.0-aosc-main.conf").is_some());
+    /// ```
+    pub fn get_entry(&self, id: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.has_id(id))
+    }
+
+    /// Get a mutable reference to the `Entry` with the given id, ignoring a trailing `.conf` on
+    /// both sides.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+    /// systemd_boot_conf
+    ///     .get_entry_mut("5.12.0-aosc-main")
+    ///     .unwrap()
+    ///     .set_title("AOSC OS");
+    /// ```
+    pub fn get_entry_mut(&mut self, id: &str) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|entry| entry.has_id(id))
+    }
+
+    /// Get the entry systemd-boot would boot by default.
+    ///
+    /// The entry is selected the way systemd-boot does: the first entry in menu order, see
+    /// `SystemdBootConf::entries_in_menu_order()`. If `default` is set in the configuration and
+    /// matches any entry (see `Config::matches_default()`), the first matching entry in menu order
+    /// wins instead.
+    ///
+    /// This deliberately differs from picking the highest `sort-key`, then the highest `version`,
+    /// then the id: systemd-boot sorts `sort-key` ascending and puts entries without one last, so
+    /// the highest `sort-key` would name an entry other than the one it actually boots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{EntryBuilder, SystemdBootConfBuilder};
+    ///
+    /// let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+    ///     .entry(EntryBuilder::new("5.9.0-aosc-main").version("5.9.0").build())
+    ///     .entry(EntryBuilder::new("5.10.0-aosc-main").version("5.10.0").build())
+    ///     .build();
+    ///
+    /// assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "5.10.0-aosc-main");
+    /// ```
+    pub fn effective_default(&self) -> Option<&Entry> {
+        let first = |a: &&Entry, b: &&Entry| cmp_menu_order(a, b);
+
+        self.entries
+            .iter()
+            .filter(|entry| self.config.matches_default(entry))
+            .min_by(first)
+            .or_else(|| self.entries.iter().min_by(first))
+    }
+
+    /// Clone the entry with the given id, ignoring a trailing `.conf`, as a template for a new
+    /// entry with `new_id`.
+    ///
+    /// The clone is returned without being added, so that its tokens can be changed first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{EntryBuilder, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(
+    ///     EntryBuilder::new("5.12.0-aosc-main")
+    ///         .title("AOSC OS")
+    ///         .version("5.12.0-aosc-main")
+    ///         .build(),
+    /// );
+    ///
+    /// let mut entry = systemd_boot_conf
+    ///     .clone_entry("5.12.0-aosc-main", "5.13.0-aosc-main")
+    ///     .unwrap();
+    ///
+    /// entry.set_version("5.13.0-aosc-main");
+    /// systemd_boot_conf.add_entry(entry);
+    /// ```
+    pub fn clone_entry(&self, source_id: &str, new_id: &str) -> Option<Entry> {
+        let mut entry = self.get_entry(source_id)?.clone();
+
+        entry.id = new_id.to_owned();
+
+        Some(entry)
+    }
+
+    /// Iterate over the entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+    ///
+    /// for entry in systemd_boot_conf.entries_iter() {
+    ///     println!("{}", entry.id);
+    /// }
+    /// ```
+    pub fn entries_iter(&self) -> std::slice::Iter<'_, Entry> {
+        self.entries.iter()
+    }
+
+    /// Iterate over mutable references to the entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+    ///
+    /// for entry in systemd_boot_conf.entries_iter_mut() {
+    ///     entry.set_title("AOSC OS");
+    /// }
+    /// ```
+    pub fn entries_iter_mut(&mut self) -> std::slice::IterMut<'_, Entry> {
+        self.entries.iter_mut()
+    }
+
+    /// Set the entry with the given id as the default boot entry, ignoring a trailing `.conf`.
+    ///
+    /// Returns `LibSDBootConfError::EntryNotFound` and leaves the configuration untouched if there
+    /// is no such entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+    /// systemd_boot_conf.set_default_entry("5.12.0-aosc-main").unwrap();
+    ///
+    /// assert_eq!(
+    ///     systemd_boot_conf.config.default,
+    ///     Some("5.12.0-aosc-main.conf".to_owned())
+    /// );
+    /// ```
+    pub fn set_default_entry(&mut self, id: &str) -> Result<(), LibSDBootConfError> {
+        let filename = self
+            .get_entry(id)
+            .ok_or_else(|| LibSDBootConfError::EntryNotFound(id.to_owned()))?
+            .filename();
+
+        self.config.default = Some(filename);
+
+        Ok(())
+    }
+
+    /// Repair the default of the configuration if it does not match any entry, see
+    /// `Config::matches_default()`.
+    ///
+    /// With `DefaultRepair::Repoint`, the default is unset if there is no entry to point to.
+    /// Returns whether the configuration has been changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Config, DefaultRepair, Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::new(
+    ///     "/efi/loader",
+    ///     Config::new(Some("5.11.0-aosc-main"), None::<u32>),
+    ///     vec![Entry::new("5.12.0-aosc-main", Vec::new())],
+    /// );
+    ///
+    /// assert!(systemd_boot_conf.ensure_default_valid(DefaultRepair::Repoint));
+    /// assert_eq!(
+    ///     systemd_boot_conf.config.default,
+    ///     Some("5.12.0-aosc-main.conf".to_owned())
+    /// );
+    /// ```
+    pub fn ensure_default_valid(&mut self, repair: DefaultRepair) -> bool {
+        if !matches!(self.default_health(), DefaultHealth::Dangling(_)) {
+            return false;
         }
+
+        self.config.default = match repair {
+            DefaultRepair::Clear => None,
+            DefaultRepair::Repoint => self
+                .entries_in_menu_order()
+                .first()
+                .map(|entry| entry.filename()),
+        };
+
+        true
     }
 
-    generate_builder_method!(
-        /// Add a systemd-boot loader `Config`.
-        plain INNER(inner) config(Config)
-    );
-    generate_builder_method!(
-        /// Add a list of `Entry`.
-        into INNER(inner) entries(E: Vec<Entry>)
-    );
+    /// Delete the file of the entry with the given id from the `entries` directory.
+    ///
+    /// The in-memory entries are left untouched, use `remove_entry` to remove the entry itself.
+    /// Returns `LibSDBootConfError::InvalidEntryId` without deleting anything if the id is not
+    /// valid, see `Entry::validate_id()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+    ///
+    /// systemd_boot_conf.remove_entry("5.12.0-aosc-main");
+    /// systemd_boot_conf.remove_entry_file("5.12.0-aosc-main").unwrap();
+    /// ```
+    pub fn remove_entry_file(&self, id: &str) -> Result<(), LibSDBootConfError> {
+        let entry = Entry::new(id, Vec::new());
 
-    /// Add an `Entry`
-    pub fn entry(mut self, entry: Entry) -> Self {
-        self.inner.entries.push(entry);
+        entry.validate_id()?;
 
-        self
+        let path = self.working_dir.join("entries").join(entry.filename());
+
+        fs::remove_file(&path)?;
+        self.audit(AuditAction::Delete, path);
+
+        Ok(())
     }
 
-    /// Build the `SystemdBootConf`.
-    pub fn build(self) -> SystemdBootConf {
-        self.inner
+    /// Find the `.conf` files in the `entries` directory that do not belong to any entry, such as
+    /// the ones left over by an uninstalled kernel.
+    ///
+    /// The paths are returned sorted. A missing `entries` directory has no orphaned files.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+    ///
+    /// for path in systemd_boot_conf.orphaned_entry_files().unwrap() {
+    ///     println!("{}", path.display());
+    /// }
+    /// ```
+    pub fn orphaned_entry_files(&self) -> Result<Vec<PathBuf>, LibSDBootConfError> {
+        let files = match fs::read_dir(self.working_dir.join("entries")) {
+            Ok(files) => files,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut orphaned_files = Vec::new();
+
+        for file in files {
+            let path = file?.path();
+
+            if path.is_file()
+                && path.extension().is_some_and(|ext| ext == "conf")
+                && !self.entries.iter().any(|entry| {
+                    path.file_name()
+                        .is_some_and(|name| *name == *entry.filename())
+                })
+            {
+                orphaned_files.push(path);
+            }
+        }
+
+        orphaned_files.sort();
+
+        Ok(orphaned_files)
+    }
+
+    /// Delete the files found by `orphaned_entry_files` and return their paths.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+    ///
+    /// systemd_boot_conf.remove_entry("5.12.0-aosc-main");
+    /// systemd_boot_conf.prune_orphaned_entry_files().unwrap();
+    /// ```
+    pub fn prune_orphaned_entry_files(&self) -> Result<Vec<PathBuf>, LibSDBootConfError> {
+        let orphaned_files = self.orphaned_entry_files()?;
+
+        for path in orphaned_files.iter() {
+            fs::remove_file(path)?;
+            self.audit(AuditAction::Delete, path);
+        }
+
+        Ok(orphaned_files)
+    }
+
+    /// Sort the entries by their `sort-key`.
+    ///
+    /// Entries without a `sort-key` are placed last, and ties are broken by the id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{EntryBuilder, SystemdBootConfBuilder};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+    ///     .entry(EntryBuilder::new("windows").build())
+    ///     .entry(EntryBuilder::new("5.12.0-aosc-main").sort_key("aosc").build())
+    ///     .build();
+    ///
+    /// systemd_boot_conf.sort_entries_by_sort_key();
+    ///
+    /// assert_eq!(systemd_boot_conf.entries[0].id, "5.12.0-aosc-main");
+    /// ```
+    pub fn sort_entries_by_sort_key(&mut self) {
+        self.entries.sort_by(cmp_sort_key);
+    }
+
+    /// Get the entries in the order systemd-boot shows them in the menu.
+    ///
+    /// Entries with a `sort-key` come first, ordered by `sort-key`, then by `machine-id`, then by
+    /// `version` in descending order. Remaining ties, and entries without a `sort-key`, are
+    /// ordered by id in descending order, so that newer kernels come first. Versions and ids are
+    /// compared with `util::compare_versions()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{EntryBuilder, SystemdBootConfBuilder};
+    ///
+    /// let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+    ///     .entry(EntryBuilder::new("5.9.0-aosc-main").sort_key("aosc").version("5.9.0").build())
+    ///     .entry(EntryBuilder::new("5.12.0-aosc-main").sort_key("aosc").version("5.12.0").build())
+    ///     .build();
+    ///
+    /// assert_eq!(systemd_boot_conf.entries_in_menu_order()[0].id, "5.12.0-aosc-main");
+    /// ```
+    pub fn entries_in_menu_order(&self) -> Vec<&Entry> {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+
+        entries.sort_by(|a, b| cmp_menu_order(a, b));
+
+        entries
+    }
+
+    /// Write systemd-boot configuration file to the system.
+    ///
+    /// The working directory is created if it does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.write_config().unwrap();
+    /// ```
+    pub fn write_config(&self) -> Result<(), LibSDBootConfError> {
+        let path = self.working_dir.join("loader.conf");
+        let action = self.write_action(&path);
+
+        fs::create_dir_all(&self.working_dir)?;
+        self.config.write(&path)?;
+        self.audit(action, path);
+
+        Ok(())
+    }
+
+    /// Write all entries to the system.
+    ///
+    /// The `entries` directory is created if it does not exist and there is any entry. Nothing is
+    /// written if the id of any entry is invalid, see `Entry::validate_id()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.write_entries().unwrap();
+    /// ```
+    pub fn write_entries(&self) -> Result<(), LibSDBootConfError> {
+        for entry in self.entries.iter() {
+            entry.validate_id()?;
+        }
+
+        self.write_entries_with(|entry| PathBuf::from(entry.filename()))
+    }
+
+    /// Write all entries to the system, with a filename chosen by `namer` for each entry.
+    ///
+    /// The paths returned by `namer` are filenames in the `entries` directory, which is created if
+    /// it does not exist and there is any entry. Unlike `write_entries()`, the ids of the entries
+    /// are not validated. Instead, nothing is written and
+    /// `LibSDBootConfError::InvalidEntryFilename` is returned if any path is not a plain filename,
+    /// such as an absolute path or one with a directory or `..` in it, or
+    /// `LibSDBootConfError::DuplicateEntryFilename` if two entries would be written to the same
+    /// file, see `filename_collisions()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    /// use std::path::PathBuf;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+    ///
+    /// systemd_boot_conf
+    ///     .write_entries_with(|entry| PathBuf::from(format!("auto-{}", entry.filename())))
+    ///     .unwrap();
+    /// ```
+    pub fn write_entries_with<F: Fn(&Entry) -> PathBuf>(
+        &self,
+        namer: F,
+    ) -> Result<(), LibSDBootConfError> {
+        let filenames = self.entry_filenames(namer)?;
+
+        if !self.entries.is_empty() {
+            fs::create_dir_all(self.working_dir.join("entries"))?;
+        }
+
+        for (entry, filename) in self.entries.iter().zip(filenames) {
+            let path = self.working_dir.join("entries").join(filename);
+            let action = self.write_action(&path);
+
+            entry.write(&path)?;
+            self.audit(action, path);
+        }
+
+        Ok(())
+    }
+
+    /// The filenames chosen by `namer` for every entry, checked as described in
+    /// `write_entries_with()`.
+    fn entry_filenames<F: Fn(&Entry) -> PathBuf>(
+        &self,
+        namer: F,
+    ) -> Result<Vec<PathBuf>, LibSDBootConfError> {
+        let mut seen = BTreeSet::new();
+
+        self.entries
+            .iter()
+            .map(|entry| {
+                let filename = namer(entry);
+                let mut components = filename.components();
+
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(_)), None) if seen.insert(filename.clone()) => {
+                        Ok(filename)
+                    }
+                    (Some(Component::Normal(_)), None) => {
+                        Err(LibSDBootConfError::DuplicateEntryFilename(filename))
+                    }
+                    _ => Err(LibSDBootConfError::InvalidEntryFilename(filename)),
+                }
+            })
+            .collect()
+    }
+
+    /// Write the entries whose file content differs from what would be written.
+    ///
+    /// Entry files that already hold the serialized entry are left untouched. Returns the paths
+    /// of the files written. As with `write_entries()`, nothing is written if the id of any entry
+    /// is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+    ///
+    /// assert!(systemd_boot_conf.write_entries_if_changed().unwrap().is_empty());
+    /// ```
+    pub fn write_entries_if_changed(&self) -> Result<Vec<PathBuf>, LibSDBootConfError> {
+        for entry in self.entries.iter() {
+            entry.validate_id()?;
+        }
+
+        if !self.entries.is_empty() {
+            fs::create_dir_all(self.working_dir.join("entries"))?;
+        }
+
+        let mut written = Vec::new();
+
+        for entry in self.entries.iter() {
+            let path = self.working_dir.join("entries").join(entry.filename());
+
+            if let Ok(existing) = fs::read_to_string(&path) {
+                if existing == entry.to_string() {
+                    continue;
+                }
+            }
+
+            let action = self.write_action(&path);
+
+            entry.write(&path)?;
+            self.audit(action, path.clone());
+            written.push(path);
+        }
+
+        Ok(written)
+    }
+
+    /// Check that the configuration and all entries parse back to the same content after being
+    /// serialized.
+    ///
+    /// Returns `LibSDBootConfError::RoundTripMismatch` with the path of the first file that would
+    /// not be read back as written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{EntryBuilder, SystemdBootConfBuilder};
+    ///
+    /// let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+    ///     .entry(EntryBuilder::new("5.12.0-aosc-main").title("AOSC OS").build())
+    ///     .build();
+    ///
+    /// assert!(systemd_boot_conf.verify_round_trip().is_ok());
+    /// ```
+    pub fn verify_round_trip(&self) -> Result<(), LibSDBootConfError> {
+        if Config::from_str(&self.config.to_string()).ok().as_ref() != Some(&self.config) {
+            return Err(LibSDBootConfError::RoundTripMismatch(
+                self.working_dir.join("loader.conf"),
+            ));
+        }
+
+        for entry in self.entries.iter() {
+            let parsed = Entry::from_str(&entry.to_string()).ok().map(|mut parsed| {
+                parsed.id = entry.id.clone();
+                parsed
+            });
+
+            if parsed.as_ref() != Some(entry) {
+                return Err(LibSDBootConfError::RoundTripMismatch(
+                    self.working_dir.join("entries").join(entry.filename()),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the whole installation and collect every problem found into a `Report`.
+    ///
+    /// If `esp_root` is given, the files referenced by the `linux`, `initrd`, `efi`, `devicetree`
+    /// and `devicetree-overlay` tokens of every entry are looked up relative to it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    /// use std::path::Path;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+    /// let report = systemd_boot_conf.full_report(Some(Path::new("/efi")));
+    ///
+    /// assert!(report.is_healthy());
+    /// ```
+    pub fn full_report(&self, esp_root: Option<&Path>) -> Report {
+        let mut report = Report::default();
+        let config_path = self.working_dir.join("loader.conf");
+
+        if !config_path.exists() {
+            report
+                .config_issues
+                .push(ConfigIssue::MissingConfigFile(config_path));
+        }
+
+        for entry in self.entries.iter() {
+            if entry.validate_id().is_err() {
+                report
+                    .entry_issues
+                    .entry(entry.id.clone())
+                    .or_default()
+                    .push(EntryIssue::InvalidId);
+            }
+        }
+
+        if let Some(esp_root) = esp_root {
+            for entry in self.entries.iter() {
+                let issues = entry
+                    .validate_paths(esp_root)
+                    .err()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(EntryIssue::MissingFile)
+                    .collect::<Vec<_>>();
+
+                if !issues.is_empty() {
+                    report
+                        .entry_issues
+                        .entry(entry.id.clone())
+                        .or_default()
+                        .extend(issues);
+                }
+            }
+        }
+
+        report.filename_collisions = self
+            .filename_collisions()
+            .into_iter()
+            .map(|(filename, entries)| {
+                (
+                    filename,
+                    entries.iter().map(|entry| entry.id.clone()).collect(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        report.orphaned_files = self.orphaned_entry_files().unwrap_or_default();
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let group = self.entries[i..]
+                .iter()
+                .filter(|other| other.tokens == entry.tokens)
+                .map(|other| other.id.clone())
+                .collect::<Vec<_>>();
+
+            if group.len() > 1
+                && !self.entries[..i]
+                    .iter()
+                    .any(|other| other.tokens == entry.tokens)
+            {
+                report.duplicate_content.push(group);
+            }
+        }
+
+        report.default_health = self.default_health();
+
+        report
+    }
+
+    /// Count the entries by their kind, see `Entry::kind()`, and check the default entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{report::DefaultHealth, EntryBuilder, SystemdBootConfBuilder};
+    ///
+    /// let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+    ///     .entry(EntryBuilder::new("5.12.0-aosc-main").linux("/vmlinuz").build())
+    ///     .build();
+    /// let summary = systemd_boot_conf.summary();
+    ///
+    /// assert_eq!(summary.linux, 1);
+    /// assert_eq!(summary.default_health, DefaultHealth::Unset);
+    /// ```
+    pub fn summary(&self) -> ConfSummary {
+        let mut summary = ConfSummary {
+            total: self.entries.len(),
+            default_health: self.default_health(),
+            ..Default::default()
+        };
+
+        for entry in self.entries.iter() {
+            match entry.kind() {
+                EntryKind::Linux => summary.linux += 1,
+                EntryKind::Efi => summary.efi += 1,
+                EntryKind::Unknown => summary.unknown += 1,
+            }
+        }
+
+        summary
+    }
+
+    /// Check whether the `default` of the configuration selects any entry.
+    fn default_health(&self) -> DefaultHealth {
+        match &self.config.default {
+            None => DefaultHealth::Unset,
+            Some(default) => match self
+                .entries
+                .iter()
+                .find(|entry| self.config.matches_default(entry))
+            {
+                Some(entry) => DefaultHealth::Valid(entry.id.clone()),
+                None => DefaultHealth::Dangling(default.clone()),
+            },
+        }
+    }
+
+    /// Compare with another configuration, e.g. a planned change, and describe what would differ
+    /// on disk.
+    ///
+    /// Entries are matched by id, ignoring a trailing `.conf`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConfBuilder};
+    ///
+    /// let current = SystemdBootConfBuilder::new("/efi/loader").build();
+    /// let planned = SystemdBootConfBuilder::new("/efi/loader")
+    ///     .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+    ///     .build();
+    ///
+    /// assert_eq!(current.diff(&planned).added_entries, vec!["5.12.0-aosc-main"]);
+    /// ```
+    pub fn diff(&self, other: &SystemdBootConf) -> ConfDiff {
+        let lines = |config: &Config| {
+            config
+                .to_string()
+                .lines()
+                .map(|line| {
+                    let (key, value) = parser::split_key_value(line);
+
+                    (key.to_owned(), value.unwrap_or_default().to_owned())
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let (old, new) = (lines(&self.config), lines(&other.config));
+        let mut diff = ConfDiff {
+            config_changes: old
+                .keys()
+                .chain(new.keys())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .filter(|key| old.get(*key) != new.get(*key))
+                .map(|key| ConfigChange {
+                    key: key.clone(),
+                    old: old.get(key).cloned(),
+                    new: new.get(key).cloned(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        for entry in self.entries.iter() {
+            match other.get_entry(&entry.id) {
+                None => diff.removed_entries.push(entry.id.clone()),
+                Some(other) if other.tokens != entry.tokens => {
+                    diff.modified_entries.push(entry.id.clone())
+                }
+                Some(_) => (),
+            }
+        }
+
+        diff.added_entries = other
+            .entries
+            .iter()
+            .filter(|entry| self.get_entry(&entry.id).is_none())
+            .map(|entry| entry.id.clone())
+            .collect();
+
+        diff
+    }
+
+    /// Set a hook to be called on every file created, modified, or deleted by the write operations.
+    ///
+    /// No hook is set by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.set_audit_hook(Box::new(|event| {
+    ///     println!("{:?} {}", event.action, event.path.display());
+    /// }));
+    /// ```
+    pub fn set_audit_hook(&mut self, hook: Box<dyn Fn(&AuditEvent)>) {
+        self.audit_hook = Some(AuditHook(hook));
+    }
+
+    /// Determine whether writing to `path` creates or modifies a file.
+    fn write_action(&self, path: &Path) -> AuditAction {
+        if path.exists() {
+            AuditAction::Modify
+        } else {
+            AuditAction::Create
+        }
+    }
+
+    /// Report a change to the audit hook, if any.
+    fn audit<P: Into<PathBuf>>(&self, action: AuditAction, path: P) {
+        if let Some(AuditHook(hook)) = &self.audit_hook {
+            hook(&AuditEvent::new(action, path));
+        }
+    }
+
+    /// Find entries that would be written to the same file.
+    ///
+    /// Entries are grouped by `Entry::filename()`, and every group with more than one entry is
+    /// returned along with the shared filename. Writing such entries with `write_entries` would
+    /// silently overwrite all but the last one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    /// systemd_boot_conf.entries.push(Entry::new("5.12.0-aosc-main", Vec::new()));
+    /// systemd_boot_conf.entries.push(Entry::new("5.12.0-aosc-main.conf", Vec::new()));
+    ///
+    /// let collisions = systemd_boot_conf.filename_collisions();
+    ///
+    /// assert_eq!(collisions.len(), 1);
+    /// assert_eq!(collisions[0].0, "5.12.0-aosc-main.conf");
+    /// ```
+    pub fn filename_collisions(&self) -> Vec<(String, Vec<&Entry>)> {
+        let mut groups: Vec<(String, Vec<&Entry>)> = Vec::new();
+
+        for entry in self.entries.iter() {
+            let filename = entry.filename();
+
+            match groups.iter_mut().find(|(name, _)| *name == filename) {
+                Some((_, group)) => group.push(entry),
+                None => groups.push((filename, vec![entry])),
+            }
+        }
+
+        groups.retain(|(_, group)| group.len() > 1);
+
+        groups
+    }
+
+    /// Group entries by their `MachineID`, with `None` for entries without one.
+    ///
+    /// Entries keep their order within each group.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Entry, SystemdBootConf};
+    ///
+    /// let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    /// systemd_boot_conf.entries.push(Entry::builder("a").machine_id("0123").build());
+    /// systemd_boot_conf.entries.push(Entry::builder("b").build());
+    ///
+    /// let groups = systemd_boot_conf.entries_by_machine_id();
+    ///
+    /// assert_eq!(groups[&Some("0123".to_owned())].len(), 1);
+    /// assert_eq!(groups[&None].len(), 1);
+    /// ```
+    pub fn entries_by_machine_id(&self) -> HashMap<Option<String>, Vec<&Entry>> {
+        let mut groups: HashMap<Option<String>, Vec<&Entry>> = HashMap::new();
+
+        for entry in self.entries.iter() {
+            groups
+                .entry(entry.machine_id().map(|s| s.to_owned()))
+                .or_default()
+                .push(entry);
+        }
+
+        groups
+    }
+
+    /// Write all configurations and entries to the system.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+    ///
+    /// systemd_boot_conf.write_all().unwrap();
+    /// ```
+    pub fn write_all(&self) -> Result<(), LibSDBootConfError> {
+        self.write_all_reporting()?;
+
+        Ok(())
+    }
+
+    /// Write all configurations and entries to the system, and return the paths of the written
+    /// files.
+    ///
+    /// The path of `loader.conf` comes first, followed by the path of every entry in order. As
+    /// with `write_entries()`, nothing is written, not even `loader.conf`, if the id of any entry is
+    /// invalid or two entries would be written to the same file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsdbootconf::SystemdBootConf;
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+    ///
+    /// for path in systemd_boot_conf.write_all_reporting().unwrap() {
+    ///     println!("wrote {}", path.display());
+    /// }
+    /// ```
+    pub fn write_all_reporting(&self) -> Result<Vec<PathBuf>, LibSDBootConfError> {
+        for entry in self.entries.iter() {
+            entry.validate_id()?;
+        }
+
+        self.entry_filenames(|entry| PathBuf::from(entry.filename()))?;
+        self.write_config()?;
+        self.write_entries()?;
+
+        Ok(std::iter::once(self.working_dir.join("loader.conf"))
+            .chain(
+                self.entries
+                    .iter()
+                    .map(|entry| self.working_dir.join("entries").join(entry.filename())),
+            )
+            .collect())
+    }
+
+    /// The files `write_all()` would write, along with their content, without touching the
+    /// system.
+    ///
+    /// The path of `loader.conf` comes first, followed by the path of every entry in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsdbootconf::{Config, Entry, SystemdBootConf};
+    ///
+    /// let systemd_boot_conf = SystemdBootConf::new(
+    ///     "/efi/loader",
+    ///     Config::new(Some("5.12.0-aosc-main"), Some(5u32)),
+    ///     vec![Entry::new("5.12.0-aosc-main", Vec::new())],
+    /// );
+    ///
+    /// for (path, content) in systemd_boot_conf.plan() {
+    ///     println!("{}:\n{}", path.display(), content);
+    /// }
+    /// ```
+    pub fn plan(&self) -> Vec<(PathBuf, String)> {
+        std::iter::once((
+            self.working_dir.join("loader.conf"),
+            self.config.to_string(),
+        ))
+        .chain(self.entries.iter().map(|entry| {
+            (
+                self.working_dir.join("entries").join(entry.filename()),
+                entry.to_string(),
+            )
+        }))
+        .collect()
+    }
+}
+
+impl IntoIterator for SystemdBootConf {
+    type Item = Entry;
+    type IntoIter = std::vec::IntoIter<Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// Iterate over the entries of a `SystemdBootConf`.
+///
+/// # Examples
+///
+/// ```
+/// use libsdbootconf::{Entry, SystemdBootConfBuilder};
+///
+/// let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+///     .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+///     .entry(Entry::new("5.13.0-aosc-main", Vec::new()))
+///     .build();
+///
+/// for entry in &systemd_boot_conf {
+///     println!("{}", entry.id);
+/// }
+/// ```
+impl<'a> IntoIterator for &'a SystemdBootConf {
+    type Item = &'a Entry;
+    type IntoIter = std::slice::Iter<'a, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// Compare entries by their `sort-key`, placing entries without one last, then by their id.
+fn cmp_sort_key(a: &Entry, b: &Entry) -> Ordering {
+    match (a.sort_key(), b.sort_key()) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+    .then_with(|| a.id.cmp(&b.id))
+}
+
+/// Compare entries in the order systemd-boot shows them in the menu.
+fn cmp_menu_order(a: &Entry, b: &Entry) -> Ordering {
+    match (a.sort_key(), b.sort_key()) {
+        (Some(sort_key_a), Some(sort_key_b)) => sort_key_a
+            .cmp(sort_key_b)
+            .then_with(|| a.machine_id().cmp(&b.machine_id()))
+            .then_with(|| match (a.version(), b.version()) {
+                (Some(a), Some(b)) => util::compare_versions(b, a),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            }),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+    .then_with(|| util::compare_versions(&b.id, &a.id))
+}
+
+/// Builder for `SystemdBootConf`.
+#[derive(Default, Debug)]
+pub struct SystemdBootConfBuilder {
+    inner: SystemdBootConf,
+}
+
+impl SystemdBootConfBuilder {
+    /// Create an empty `SystemdBootConfBuilder` with a working directory.
+    pub fn new<P: Into<PathBuf>>(working_dir: P) -> Self {
+        Self {
+            inner: SystemdBootConf::init(working_dir),
+        }
+    }
+
+    generate_builder_method!(
+        /// Add a systemd-boot loader `Config`.
+        plain INNER(inner) config(Config)
+    );
+    generate_builder_method!(
+        /// Add a list of `Entry`.
+        into INNER(inner) entries(E: Vec<Entry>)
+    );
+
+    /// Add an `Entry`
+    pub fn entry(mut self, entry: Entry) -> Self {
+        self.inner.entries.push(entry);
+
+        self
+    }
+
+    /// Build the `SystemdBootConf`.
+    pub fn build(self) -> SystemdBootConf {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_filename_collisions() {
+        let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .entry(Entry::new("5.12.0-aosc-main.conf", Vec::new()))
+            .entry(Entry::new("5.13.0-aosc-main", Vec::new()))
+            .build();
+
+        let collisions = systemd_boot_conf.filename_collisions();
+
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].0, "5.12.0-aosc-main.conf");
+        assert_eq!(
+            collisions[0]
+                .1
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["5.12.0-aosc-main", "5.12.0-aosc-main.conf"]
+        );
+    }
+
+    #[test]
+    fn test_audit_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("entries")).unwrap();
+        fs::write(dir.path().join("entries/5.12.0-aosc-main.conf"), "").unwrap();
+
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .entry(Entry::new("5.13.0-aosc-main", Vec::new()))
+            .build();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&events);
+
+        systemd_boot_conf.set_audit_hook(Box::new(move |event| {
+            recorded.borrow_mut().push(event.clone());
+        }));
+        systemd_boot_conf.write_all().unwrap();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                AuditEvent::new(AuditAction::Create, dir.path().join("loader.conf")),
+                AuditEvent::new(
+                    AuditAction::Modify,
+                    dir.path().join("entries/5.12.0-aosc-main.conf")
+                ),
+                AuditEvent::new(
+                    AuditAction::Create,
+                    dir.path().join("entries/5.13.0-aosc-main.conf")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_map() {
+        let files = HashMap::from([
+            (
+                PathBuf::from("loader.conf"),
+                "default 5.12.0-aosc-main.conf\ntimeout 5\n".to_owned(),
+            ),
+            (
+                PathBuf::from("entries/5.13.0-aosc-main.conf"),
+                "title AOSC OS (5.13.0-aosc-main)\n".to_owned(),
+            ),
+            (
+                PathBuf::from("entries/5.12.0-aosc-main.conf"),
+                "title AOSC OS (5.12.0-aosc-main)\nlinux /vmlinuz-5.12.0-aosc-main\n".to_owned(),
+            ),
+            (PathBuf::from("entries.srel"), "type1\n".to_owned()),
+        ]);
+
+        let systemd_boot_conf = SystemdBootConf::from_map("/efi/loader", files).unwrap();
+
+        assert_eq!(
+            systemd_boot_conf.config,
+            Config::new(Some("5.12.0-aosc-main.conf"), Some(5u32))
+        );
+        assert_eq!(
+            systemd_boot_conf.entries,
+            vec![
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .title("AOSC OS (5.12.0-aosc-main)")
+                    .linux("/vmlinuz-5.12.0-aosc-main")
+                    .build(),
+                EntryBuilder::new("5.13.0-aosc-main")
+                    .title("AOSC OS (5.13.0-aosc-main)")
+                    .build(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_map_without_config() {
+        let files = HashMap::from([(
+            PathBuf::from("entries/5.12.0-aosc-main.conf"),
+            "title AOSC OS\n".to_owned(),
+        )]);
+
+        assert!(SystemdBootConf::from_map("/efi/loader", files).is_err());
+    }
+
+    #[test]
+    fn test_verify_round_trip() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .config(Config::new(Some("5.12.0-aosc-main"), Some(5u32)))
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .title("AOSC OS")
+                    .linux("/vmlinuz-5.12.0-aosc-main")
+                    .initrd("/initramfs-5.12.0-aosc-main.img")
+                    .options("root=/dev/sda1 rw")
+                    .build(),
+            )
+            .build();
+
+        assert!(systemd_boot_conf.verify_round_trip().is_ok());
+
+        systemd_boot_conf
+            .entries
+            .push(EntryBuilder::new("broken").title("AOSC OS\nrw").build());
+
+        assert!(matches!(
+            systemd_boot_conf.verify_round_trip(),
+            Err(LibSDBootConfError::RoundTripMismatch(path))
+                if path == Path::new("/efi/loader/entries/broken.conf")
+        ));
+    }
+
+    #[test]
+    fn test_aux_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("entries")).unwrap();
+        fs::write(dir.path().join("entries.srel"), "type1\n").unwrap();
+
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .build();
+
+        systemd_boot_conf.write_all().unwrap();
+
+        assert_eq!(
+            systemd_boot_conf.aux_files(),
+            vec![(dir.path().join("entries.srel"), "type1\n".to_owned())]
+        );
+
+        fs::write(dir.path().join("entries.srel"), b"type1\xff\n").unwrap();
+
+        assert_eq!(
+            systemd_boot_conf.aux_files(),
+            vec![(
+                dir.path().join("entries.srel"),
+                "type1\u{fffd}\n".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_full_report() {
+        let esp = tempfile::tempdir().unwrap();
+        let working_dir = esp.path().join("loader");
+        fs::create_dir_all(working_dir.join("entries")).unwrap();
+        fs::write(esp.path().join("vmlinuz-5.12.0-aosc-main"), "").unwrap();
+        fs::write(working_dir.join("entries/5.11.0-aosc-main.conf"), "").unwrap();
+
+        let systemd_boot_conf = SystemdBootConfBuilder::new(&working_dir)
+            .config(Config::new(Some("5.13.0-aosc-main"), None::<u32>))
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .title("AOSC OS")
+                    .linux("/vmlinuz-5.12.0-aosc-main")
+                    .initrd("/initramfs-5.12.0-aosc-main.img")
+                    .build(),
+            )
+            .entry(EntryBuilder::new("fallback").title("Fallback").build())
+            .entry(EntryBuilder::new("fallback.conf").title("Fallback").build())
+            .build();
+
+        let report = systemd_boot_conf.full_report(Some(esp.path()));
+
+        assert!(!report.is_healthy());
+        assert_eq!(
+            report.config_issues,
+            vec![ConfigIssue::MissingConfigFile(
+                working_dir.join("loader.conf")
+            )]
+        );
+        assert_eq!(
+            report.entry_issues,
+            BTreeMap::from([(
+                "5.12.0-aosc-main".to_owned(),
+                vec![EntryIssue::MissingFile(PathBuf::from(
+                    "/initramfs-5.12.0-aosc-main.img"
+                ))]
+            )])
+        );
+        assert_eq!(
+            report.filename_collisions,
+            BTreeMap::from([(
+                "fallback.conf".to_owned(),
+                vec!["fallback".to_owned(), "fallback.conf".to_owned()]
+            )])
+        );
+        assert_eq!(
+            report.orphaned_files,
+            vec![working_dir.join("entries/5.11.0-aosc-main.conf")]
+        );
+        assert_eq!(
+            report.duplicate_content,
+            vec![vec!["fallback".to_owned(), "fallback.conf".to_owned()]]
+        );
+        assert_eq!(
+            report.default_health,
+            DefaultHealth::Dangling("5.13.0-aosc-main".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_remove_entry() {
+        let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+
+        systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+        systemd_boot_conf.add_entry(Entry::new("5.13.0-aosc-main.conf", Vec::new()));
+
+        assert_eq!(systemd_boot_conf.remove_entry("5.11.0-aosc-main"), None);
+        assert_eq!(
+            systemd_boot_conf.remove_entry("5.13.0-aosc-main"),
+            Some(Entry::new("5.13.0-aosc-main.conf", Vec::new()))
+        );
+        assert_eq!(
+            systemd_boot_conf.entries,
+            vec![Entry::new("5.12.0-aosc-main", Vec::new())]
+        );
+    }
+
+    #[test]
+    fn test_remove_entry_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .build();
+
+        fs::create_dir(dir.path().join("entries")).unwrap();
+        systemd_boot_conf.write_entries().unwrap();
+        systemd_boot_conf
+            .remove_entry_file("5.12.0-aosc-main")
+            .unwrap();
+
+        assert!(!dir.path().join("entries/5.12.0-aosc-main.conf").exists());
+        assert_eq!(systemd_boot_conf.entries.len(), 1);
+        assert!(systemd_boot_conf
+            .remove_entry_file("5.12.0-aosc-main")
+            .is_err());
+    }
+
+    #[test]
+    fn test_remove_entry_file_invalid_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path()).build();
+
+        fs::create_dir(dir.path().join("entries")).unwrap();
+        systemd_boot_conf.write_config().unwrap();
+
+        assert!(matches!(
+            systemd_boot_conf.remove_entry_file("../loader"),
+            Err(LibSDBootConfError::InvalidEntryId { .. })
+        ));
+        assert!(dir.path().join("loader.conf").exists());
+    }
+
+    #[test]
+    fn test_cmp_sort_key() {
+        let aosc = EntryBuilder::new("b").sort_key("aosc").build();
+        let aosc_old = EntryBuilder::new("a").sort_key("aosc").build();
+        let fedora = EntryBuilder::new("a").sort_key("fedora").build();
+        let windows = EntryBuilder::new("windows").build();
+        let efi_shell = EntryBuilder::new("efi-shell").build();
+
+        assert_eq!(cmp_sort_key(&aosc, &fedora), Ordering::Less);
+        assert_eq!(cmp_sort_key(&aosc_old, &aosc), Ordering::Less);
+        assert_eq!(cmp_sort_key(&fedora, &windows), Ordering::Less);
+        assert_eq!(cmp_sort_key(&windows, &aosc), Ordering::Greater);
+        assert_eq!(cmp_sort_key(&efi_shell, &windows), Ordering::Less);
+        assert_eq!(cmp_sort_key(&aosc, &aosc), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort_entries_by_sort_key() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(EntryBuilder::new("windows").build())
+            .entry(EntryBuilder::new("fedora").sort_key("fedora").build())
+            .entry(EntryBuilder::new("aosc-new").sort_key("aosc").build())
+            .entry(EntryBuilder::new("aosc-old").sort_key("aosc").build())
+            .entry(EntryBuilder::new("efi-shell").build())
+            .build();
+
+        systemd_boot_conf.sort_entries_by_sort_key();
+
+        assert_eq!(
+            systemd_boot_conf
+                .entries
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["aosc-new", "aosc-old", "fedora", "efi-shell", "windows"]
+        );
+    }
+
+    #[test]
+    fn test_get_entry() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .entry(Entry::new("5.13.0-aosc-main.conf", Vec::new()))
+            .build();
+
+        for id in ["5.12.0-aosc-main", "5.12.0-aosc-main.conf"] {
+            assert_eq!(
+                systemd_boot_conf
+                    .get_entry(id)
+                    .map(|entry| entry.id.as_str()),
+                Some("5.12.0-aosc-main")
+            );
+        }
+
+        for id in ["5.13.0-aosc-main", "5.13.0-aosc-main.conf"] {
+            assert_eq!(
+                systemd_boot_conf
+                    .get_entry(id)
+                    .map(|entry| entry.id.as_str()),
+                Some("5.13.0-aosc-main.conf")
+            );
+        }
+
+        assert!(systemd_boot_conf.get_entry("5.11.0-aosc-main").is_none());
+
+        systemd_boot_conf
+            .get_entry_mut("5.13.0-aosc-main")
+            .unwrap()
+            .set_title("AOSC OS");
+
+        assert_eq!(
+            systemd_boot_conf.entries[1].tokens,
+            vec![Token::Title("AOSC OS".to_owned())]
+        );
+        assert!(systemd_boot_conf
+            .get_entry_mut("5.11.0-aosc-main")
+            .is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .config(
+                ConfigBuilder::new()
+                    .default("5.12.0-aosc-main.conf")
+                    .timeout(Timeout::MenuForce)
+                    .console_mode(ConsoleMode::Mode(2))
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .title("AOSC OS")
+                    .linux("/EFI/aosc/vmlinuz-5.12.0-aosc-main")
+                    .initrd("/EFI/aosc/initramfs-5.12.0-aosc-main.img")
+                    .build(),
+            )
+            .build();
+
+        let json = serde_json::to_string(&systemd_boot_conf).unwrap();
+
+        assert!(json.contains(r#"{"Linux":"/EFI/aosc/vmlinuz-5.12.0-aosc-main"}"#));
+
+        let deserialized: SystemdBootConf = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.working_dir, systemd_boot_conf.working_dir);
+        assert_eq!(deserialized.config, systemd_boot_conf.config);
+        assert_eq!(deserialized.entries, systemd_boot_conf.entries);
+    }
+
+    #[test]
+    fn test_set_default_entry() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .build();
+
+        systemd_boot_conf
+            .set_default_entry("5.12.0-aosc-main.conf")
+            .unwrap();
+
+        assert_eq!(
+            systemd_boot_conf.config.default,
+            Some("5.12.0-aosc-main.conf".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_set_default_entry_not_found() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .build();
+
+        assert!(matches!(
+            systemd_boot_conf.set_default_entry("5.12.0-aosc-mian"),
+            Err(LibSDBootConfError::EntryNotFound(id)) if id == "5.12.0-aosc-mian"
+        ));
+        assert_eq!(systemd_boot_conf.config.default, None);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .entry(Entry::new("5.13.0-aosc-main", Vec::new()))
+            .build();
+
+        let ids = (&systemd_boot_conf)
+            .into_iter()
+            .map(|entry| entry.id.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, vec!["5.12.0-aosc-main", "5.13.0-aosc-main"]);
+        assert_eq!(systemd_boot_conf.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_load_without_entries_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("loader.conf"), "timeout 5\n").unwrap();
+
+        let systemd_boot_conf = SystemdBootConf::load(dir.path()).unwrap();
+
+        assert_eq!(systemd_boot_conf.config.timeout, Some(Timeout::Seconds(5)));
+        assert!(systemd_boot_conf.entries.is_empty());
+    }
+
+    #[test]
+    fn test_orphaned_entry_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = dir.path().join("entries");
+        fs::create_dir(&entries).unwrap();
+        fs::write(dir.path().join("loader.conf"), "").unwrap();
+        fs::write(entries.join("5.12.0-aosc-main.conf"), "title AOSC OS\n").unwrap();
+        fs::write(entries.join("5.11.0-aosc-main.conf"), "title AOSC OS\n").unwrap();
+
+        let mut systemd_boot_conf = SystemdBootConf::load(dir.path()).unwrap();
+        systemd_boot_conf.remove_entry("5.11.0-aosc-main");
+
+        assert_eq!(
+            systemd_boot_conf.orphaned_entry_files().unwrap(),
+            vec![entries.join("5.11.0-aosc-main.conf")]
+        );
+        assert_eq!(
+            systemd_boot_conf.prune_orphaned_entry_files().unwrap(),
+            vec![entries.join("5.11.0-aosc-main.conf")]
+        );
+        assert!(!entries.join("5.11.0-aosc-main.conf").exists());
+        assert!(entries.join("5.12.0-aosc-main.conf").exists());
+        assert!(systemd_boot_conf.orphaned_entry_files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_write_all_reporting() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("entries")).unwrap();
+
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .entry(Entry::new("5.13.0-aosc-main", Vec::new()))
+            .build();
+        let written = systemd_boot_conf.write_all_reporting().unwrap();
+
+        assert_eq!(
+            written,
+            vec![
+                dir.path().join("loader.conf"),
+                dir.path().join("entries/5.12.0-aosc-main.conf"),
+                dir.path().join("entries/5.13.0-aosc-main.conf"),
+            ]
+        );
+        assert!(written.iter().all(|path| path.is_file()));
+    }
+
+    #[test]
+    fn test_write_all_invalid_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .config(Config::new(Some("../5.13.0-aosc-main"), None::<u32>))
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .entry(Entry::new("../5.13.0-aosc-main", Vec::new()))
+            .build();
+
+        assert!(matches!(
+            systemd_boot_conf.write_all_reporting(),
+            Err(LibSDBootConfError::InvalidEntryId { .. })
+        ));
+        assert!(!dir.path().join("loader.conf").exists());
+        assert!(!dir.path().join("entries").exists());
+    }
+
+    #[test]
+    fn test_write_all_creates_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let working_dir = dir.path().join("loader");
+
+        SystemdBootConfBuilder::new(&working_dir)
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .build()
+            .write_all()
+            .unwrap();
+
+        assert!(working_dir.join("loader.conf").is_file());
+        assert!(working_dir.join("entries/5.12.0-aosc-main.conf").is_file());
+    }
+
+    #[test]
+    fn test_diff() {
+        let current = SystemdBootConfBuilder::new("/efi/loader")
+            .config(Config::new(Some("5.12.0-aosc-main"), Some(5u32)))
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .title("AOSC OS")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.11.0-aosc-main")
+                    .title("AOSC OS")
+                    .build(),
+            )
+            .build();
+        let planned = SystemdBootConfBuilder::new("/efi/loader")
+            .config(Config::new(Some("5.13.0-aosc-main"), None::<u32>))
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .title("AOSC OS x86_64")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.11.0-aosc-main")
+                    .title("AOSC OS")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.13.0-aosc-main")
+                    .title("AOSC OS")
+                    .build(),
+            )
+            .build();
+        let diff = current.diff(&planned);
+
+        assert_eq!(
+            diff.config_changes,
+            vec![
+                ConfigChange {
+                    key: "default".to_owned(),
+                    old: Some("5.12.0-aosc-main".to_owned()),
+                    new: Some("5.13.0-aosc-main".to_owned()),
+                },
+                ConfigChange {
+                    key: "timeout".to_owned(),
+                    old: Some("5".to_owned()),
+                    new: None,
+                },
+            ]
+        );
+        assert_eq!(diff.added_entries, vec!["5.13.0-aosc-main"]);
+        assert!(diff.removed_entries.is_empty());
+        assert_eq!(diff.modified_entries, vec!["5.12.0-aosc-main"]);
+        assert!(current.diff(&current).is_empty());
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("loader.conf"), "timeout 5\n").unwrap();
+
+        let mut systemd_boot_conf = SystemdBootConf::load(dir.path()).unwrap();
+
+        systemd_boot_conf.config.timeout = Some(Timeout::MenuForce);
+        systemd_boot_conf.add_entry(Entry::new("5.12.0-aosc-main", Vec::new()));
+        fs::write(dir.path().join("loader.conf"), "timeout 3\n").unwrap();
+        systemd_boot_conf.reload().unwrap();
+
+        assert_eq!(systemd_boot_conf.working_dir, dir.path());
+        assert_eq!(systemd_boot_conf.config.timeout, Some(Timeout::Seconds(3)));
+        assert!(systemd_boot_conf.entries.is_empty());
+    }
+
+    #[test]
+    fn test_load_skips_non_conf_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = dir.path().join("entries");
+        fs::create_dir_all(entries.join("backup")).unwrap();
+        fs::write(dir.path().join("loader.conf"), "").unwrap();
+        fs::write(entries.join("5.12.0-aosc-main.conf"), "title AOSC OS\n").unwrap();
+        fs::write(entries.join("5.11.0-aosc-main.conf.bak"), "not an entry\n").unwrap();
+
+        let systemd_boot_conf = SystemdBootConf::load(dir.path()).unwrap();
+
+        assert_eq!(systemd_boot_conf.entries.len(), 1);
+        assert_eq!(systemd_boot_conf.entries[0].id, "5.12.0-aosc-main");
+
+        fs::write(entries.join("broken.conf"), "title\n").unwrap();
+
+        assert!(SystemdBootConf::load(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_config_only() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("entries")).unwrap();
+        fs::write(dir.path().join("loader.conf"), "timeout 5\n").unwrap();
+        fs::write(
+            dir.path().join("entries/5.12.0-aosc-main.conf"),
+            "title AOSC OS\n",
+        )
+        .unwrap();
+
+        let systemd_boot_conf = SystemdBootConf::load_config_only(dir.path()).unwrap();
+
+        assert_eq!(systemd_boot_conf.config.timeout, Some(Timeout::Seconds(5)));
+        assert!(systemd_boot_conf.entries.is_empty());
+
+        fs::remove_dir_all(dir.path().join("entries")).unwrap();
+        systemd_boot_conf.write_entries().unwrap();
+
+        assert!(!dir.path().join("entries").exists());
+    }
+
+    #[test]
+    fn test_write_entries_invalid_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .entry(Entry::new("5.12.0-aosc-main", Vec::new()))
+            .entry(Entry::new("../5.13.0-aosc-main", Vec::new()))
+            .build();
+
+        assert!(matches!(
+            systemd_boot_conf.write_entries(),
+            Err(LibSDBootConfError::InvalidEntryId { .. })
+        ));
+        assert!(!dir.path().join("entries").exists());
+        assert_eq!(
+            systemd_boot_conf.full_report(None).entry_issues["../5.13.0-aosc-main"],
+            vec![EntryIssue::InvalidId]
+        );
+    }
+
+    #[test]
+    fn test_load_entries_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = vec![
+            dir.path().join("5.12.0-aosc-main.conf"),
+            dir.path().join("5.13.0-aosc-main.conf"),
+        ];
+        fs::write(&paths[0], "title AOSC OS\n").unwrap();
+        fs::write(&paths[1], "title AOSC OS\n").unwrap();
+
+        let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+
+        systemd_boot_conf.load_entries_from(paths).unwrap();
+
+        assert_eq!(systemd_boot_conf.entries[0].id, "5.12.0-aosc-main");
+        assert_eq!(systemd_boot_conf.entries[1].id, "5.13.0-aosc-main");
+
+        let missing = dir.path().join("5.14.0-aosc-main.conf");
+
+        assert!(matches!(
+            systemd_boot_conf.load_entries_from([missing.clone()]),
+            Err(LibSDBootConfError::EntryLoadError { path, .. }) if path == missing
+        ));
+        assert_eq!(systemd_boot_conf.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_clone_entry() {
+        let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .title("AOSC OS")
+                    .linux("/vmlinuz-5.12.0-aosc-main")
+                    .build(),
+            )
+            .build();
+        let entry = systemd_boot_conf
+            .clone_entry("5.12.0-aosc-main.conf", "5.13.0-aosc-main")
+            .unwrap();
+
+        assert_eq!(entry.id, "5.13.0-aosc-main");
+        assert_eq!(entry.tokens, systemd_boot_conf.entries[0].tokens);
+        assert!(systemd_boot_conf
+            .clone_entry("5.11.0-aosc-main", "5.13.0-aosc-main")
+            .is_none());
+    }
+
+    #[test]
+    fn test_effective_default() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(EntryBuilder::new("aosc-a").version("5.9.0").build())
+            .entry(EntryBuilder::new("aosc-b").version("5.10.0").build())
+            .entry(EntryBuilder::new("aosc-c").build())
+            .build();
+
+        // Without a sort-key, the first menu entry is the highest id, whatever the version.
+        assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "aosc-c");
+
+        systemd_boot_conf.entries[0].set_sort_key("aosc");
+
+        assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "aosc-a");
+
+        systemd_boot_conf.entries[1].set_sort_key("aosc");
+
+        assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "aosc-b");
+
+        systemd_boot_conf.entries[1].set_sort_key("fedora");
+
+        assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "aosc-a");
+        assert_eq!(
+            systemd_boot_conf.effective_default().unwrap().id,
+            systemd_boot_conf.entries_in_menu_order()[0].id
+        );
+
+        systemd_boot_conf.config.default = Some("aosc-c.conf".to_owned());
+
+        assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "aosc-c");
+
+        systemd_boot_conf.config.default = Some("aosc-?".to_owned());
+
+        assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "aosc-a");
+
+        systemd_boot_conf.config.default = Some("windows".to_owned());
+
+        assert_eq!(systemd_boot_conf.effective_default().unwrap().id, "aosc-a");
+        assert!(SystemdBootConf::init("/efi/loader")
+            .effective_default()
+            .is_none());
+    }
+
+    #[test]
+    fn test_write_entries_if_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut systemd_boot_conf = SystemdBootConf::new(
+            dir.path(),
+            Config::default(),
+            vec![
+                Entry::new("a", vec![Token::Title("A".to_owned())]),
+                Entry::new("b", vec![Token::Title("B".to_owned())]),
+            ],
+        );
+
+        assert_eq!(
+            systemd_boot_conf.write_entries_if_changed().unwrap().len(),
+            2
+        );
+        assert!(systemd_boot_conf
+            .write_entries_if_changed()
+            .unwrap()
+            .is_empty());
+
+        systemd_boot_conf
+            .get_entry_mut("b")
+            .unwrap()
+            .set_title("B2");
+
+        assert_eq!(
+            systemd_boot_conf.write_entries_if_changed().unwrap(),
+            vec![dir.path().join("entries/b.conf")]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("entries/b.conf")).unwrap(),
+            "title B2\n"
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let systemd_boot_conf = SystemdBootConf::new(
+            dir.path(),
+            Config::new(Some("a"), Some(5u32)),
+            vec![Entry::new("a", vec![Token::Title("A".to_owned())])],
+        );
+
+        assert_eq!(
+            systemd_boot_conf.plan(),
+            vec![
+                (
+                    dir.path().join("loader.conf"),
+                    "default a\ntimeout 5\n".to_owned()
+                ),
+                (dir.path().join("entries/a.conf"), "title A\n".to_owned()),
+            ]
+        );
+        assert!(fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_discover() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(matches!(
+            SystemdBootConf::discover(dir.path()),
+            Err(LibSDBootConfError::LoaderNotFound(path)) if path == dir.path()
+        ));
+
+        let loader = dir.path().join("efi/loader");
+
+        fs::create_dir_all(loader.join("entries")).unwrap();
+        fs::write(loader.join("loader.conf"), "default a\n").unwrap();
+        fs::write(loader.join("entries/a.conf"), "title A\n").unwrap();
+        fs::create_dir_all(dir.path().join("boot/loader")).unwrap();
+
+        let systemd_boot_conf = SystemdBootConf::discover(dir.path()).unwrap();
+
+        assert_eq!(systemd_boot_conf.working_dir, loader);
+        assert_eq!(systemd_boot_conf.config.default.as_deref(), Some("a"));
+        assert_eq!(systemd_boot_conf.entries.len(), 1);
+
+        let systemd_boot_conf = SystemdBootConf::discover(&dir.path().join("efi")).unwrap();
+
+        assert_eq!(systemd_boot_conf.working_dir, loader);
+    }
+
+    #[test]
+    fn test_write_entries_with() {
+        let dir = tempfile::tempdir().unwrap();
+        let systemd_boot_conf = SystemdBootConf::new(
+            dir.path(),
+            Config::default(),
+            vec![Entry::new("a", vec![Token::Title("A".to_owned())])],
+        );
+
+        systemd_boot_conf
+            .write_entries_with(|entry| PathBuf::from(format!("auto-{}", entry.filename())))
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("entries/auto-a.conf")).unwrap(),
+            "title A\n"
+        );
+        assert!(!dir.path().join("entries/a.conf").exists());
+
+        for filename in [
+            "../../escaped.conf",
+            "/tmp/escaped.conf",
+            "sub/a.conf",
+            "..",
+            "",
+        ] {
+            assert!(matches!(
+                systemd_boot_conf.write_entries_with(|_| PathBuf::from(filename)),
+                Err(LibSDBootConfError::InvalidEntryFilename(path)) if path == Path::new(filename)
+            ));
+        }
+
+        assert!(!dir.path().join("escaped.conf").exists());
+        assert!(!dir.path().parent().unwrap().join("escaped.conf").exists());
+        assert_eq!(fs::read_dir(dir.path().join("entries")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_entries_with_duplicate_filename() {
+        let dir = tempfile::tempdir().unwrap();
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .entry(Entry::new("a", vec![Token::Title("A".to_owned())]))
+            .entry(Entry::new("b", vec![Token::Title("B".to_owned())]))
+            .build();
+
+        assert!(matches!(
+            systemd_boot_conf.write_entries_with(|_| PathBuf::from("same.conf")),
+            Err(LibSDBootConfError::DuplicateEntryFilename(path)) if path == Path::new("same.conf")
+        ));
+        assert!(!dir.path().join("entries").exists());
+
+        let systemd_boot_conf = SystemdBootConfBuilder::new(dir.path())
+            .entry(Entry::new("a", Vec::new()))
+            .entry(Entry::new("a.conf", Vec::new()))
+            .build();
+
+        assert!(matches!(
+            systemd_boot_conf.write_all(),
+            Err(LibSDBootConfError::DuplicateEntryFilename(_))
+        ));
+        assert!(!dir.path().join("loader.conf").exists());
+    }
+
+    #[test]
+    fn test_entries_by_machine_id() {
+        let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+        systemd_boot_conf.entries = vec![
+            Entry::builder("fedora-1").machine_id("aaaa").build(),
+            Entry::builder("debian-1").machine_id("bbbb").build(),
+            Entry::builder("fedora-2").machine_id("aaaa").build(),
+            Entry::builder("memtest").build(),
+        ];
+
+        let groups = systemd_boot_conf.entries_by_machine_id();
+        let ids = |key: Option<&str>| {
+            groups[&key.map(|s| s.to_owned())]
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(ids(Some("aaaa")), vec!["fedora-1", "fedora-2"]);
+        assert_eq!(ids(Some("bbbb")), vec!["debian-1"]);
+        assert_eq!(ids(None), vec!["memtest"]);
+    }
+
+    #[test]
+    fn test_dedup_entries() {
+        let mut systemd_boot_conf = SystemdBootConf::init("/efi/loader");
+        systemd_boot_conf.entries = vec![
+            Entry::builder("a").title("A1").build(),
+            Entry::builder("b").build(),
+            Entry::builder("a.conf").title("A2").build(),
+        ];
+
+        systemd_boot_conf.dedup_entries();
+
+        assert_eq!(
+            systemd_boot_conf.entries,
+            vec![
+                Entry::builder("b").build(),
+                Entry::builder("a.conf").title("A2").build(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entries_in_menu_order() {
+        let systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .entry(EntryBuilder::new("windows").build())
+            .entry(EntryBuilder::new("5.9.0-fallback").build())
+            .entry(EntryBuilder::new("5.12.0-fallback").build())
+            .entry(
+                EntryBuilder::new("fedora")
+                    .sort_key("fedora")
+                    .machine_id("aaaa")
+                    .version("6.1.0")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.9.0-aosc-main")
+                    .sort_key("aosc")
+                    .machine_id("bbbb")
+                    .version("5.9.0")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .sort_key("aosc")
+                    .machine_id("bbbb")
+                    .version("5.12.0")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("aosc-other")
+                    .sort_key("aosc")
+                    .machine_id("aaaa")
+                    .version("5.4.0")
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(
+            systemd_boot_conf
+                .entries_in_menu_order()
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "aosc-other",
+                "5.12.0-aosc-main",
+                "5.9.0-aosc-main",
+                "fedora",
+                "windows",
+                "5.12.0-fallback",
+                "5.9.0-fallback",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_without_loader_conf() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("entries")).unwrap();
+        fs::write(dir.path().join("entries/a.conf"), "title A\n").unwrap();
+
+        let systemd_boot_conf = SystemdBootConf::load(dir.path()).unwrap();
+
+        assert_eq!(systemd_boot_conf.config, Config::default());
+        assert_eq!(systemd_boot_conf.entries.len(), 1);
+
+        fs::create_dir(dir.path().join("loader.conf")).unwrap();
+
+        assert!(matches!(
+            SystemdBootConf::load(dir.path()),
+            Err(LibSDBootConfError::IOError(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_invalid_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = dir.path().join("entries/a.conf");
+        fs::create_dir(dir.path().join("entries")).unwrap();
+        fs::write(&entry, b"title \xc3\x28\n").unwrap();
+
+        assert!(matches!(
+            Entry::load(&entry),
+            Err(LibSDBootConfError::InvalidEncoding(path)) if path == entry
+        ));
+        assert!(matches!(
+            SystemdBootConf::load(dir.path()),
+            Err(LibSDBootConfError::InvalidEncoding(path)) if path == entry
+        ));
+
+        fs::write(dir.path().join("loader.conf"), b"default \xff\n").unwrap();
+
+        assert!(matches!(
+            Config::load(dir.path().join("loader.conf")),
+            Err(LibSDBootConfError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .config(Config::new(Some("5.12.0-*"), None::<u32>))
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .linux("/vmlinuz")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.11.0-aosc-main")
+                    .linux("/vmlinuz")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("windows")
+                    .efi("/EFI/Microsoft/bootmgfw.efi")
+                    .build(),
+            )
+            .entry(EntryBuilder::new("empty").build())
+            .build();
+
+        assert_eq!(
+            systemd_boot_conf.summary(),
+            ConfSummary {
+                total: 4,
+                linux: 2,
+                efi: 1,
+                unknown: 1,
+                default_health: DefaultHealth::Valid("5.12.0-aosc-main".to_owned()),
+            }
+        );
+
+        systemd_boot_conf.config.default = Some("6.*".to_owned());
+
+        assert_eq!(
+            systemd_boot_conf.summary().default_health,
+            DefaultHealth::Dangling("6.*".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_ensure_default_valid() {
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .config(Config::new(Some("5.12.0-*"), None::<u32>))
+            .entry(
+                EntryBuilder::new("5.12.0-aosc-main")
+                    .version("5.12.0")
+                    .build(),
+            )
+            .entry(
+                EntryBuilder::new("5.13.0-aosc-main")
+                    .version("5.13.0")
+                    .build(),
+            )
+            .build();
+
+        assert!(!systemd_boot_conf.ensure_default_valid(DefaultRepair::Clear));
+        assert_eq!(
+            systemd_boot_conf.config.default.as_deref(),
+            Some("5.12.0-*")
+        );
+
+        systemd_boot_conf.remove_entry("5.12.0-aosc-main");
+
+        assert!(systemd_boot_conf.ensure_default_valid(DefaultRepair::Repoint));
+        assert_eq!(
+            systemd_boot_conf.config.default.as_deref(),
+            Some("5.13.0-aosc-main.conf")
+        );
+
+        systemd_boot_conf.config.default = Some("5.11.0-aosc-main".to_owned());
+
+        assert!(systemd_boot_conf.ensure_default_valid(DefaultRepair::Clear));
+        assert_eq!(systemd_boot_conf.config.default, None);
+        assert!(!systemd_boot_conf.ensure_default_valid(DefaultRepair::Clear));
+
+        systemd_boot_conf.config.default = Some("5.11.0-aosc-main".to_owned());
+        systemd_boot_conf.entries.clear();
+
+        assert!(systemd_boot_conf.ensure_default_valid(DefaultRepair::Repoint));
+        assert_eq!(systemd_boot_conf.config.default, None);
+    }
+
+    #[test]
+    fn test_ensure_default_valid_repoint_menu_order() {
+        let mut fedora = EntryBuilder::new("5.13.0-fedora").version("5.13.0").build();
+        let mut aosc = EntryBuilder::new("5.12.0-aosc-main")
+            .version("5.12.0")
+            .build();
+
+        fedora.push_token(Token::SortKey("fedora".to_owned()));
+        aosc.push_token(Token::SortKey("aosc".to_owned()));
+
+        let mut systemd_boot_conf = SystemdBootConfBuilder::new("/efi/loader")
+            .config(Config::new(Some("5.11.0-aosc-main"), None::<u32>))
+            .entry(fedora)
+            .entry(aosc)
+            .build();
+
+        assert!(systemd_boot_conf.ensure_default_valid(DefaultRepair::Repoint));
+        assert_eq!(
+            systemd_boot_conf.config.default.as_deref(),
+            Some("5.12.0-aosc-main.conf")
+        );
     }
 }
@@ -57,6 +57,53 @@ macro_rules! generate_builder_method {
             self
         }
     };
+
+    // Build an optional token builder method for EntryBuilder, pushing the token only for `Some`,
+    // with a real inner structure, a name of the function/parameter, a generic name, and a type
+    // of the expected parameter
+    (
+        $(#[$meta:meta])*
+        token_option $parent:ident::$token:ident INNER($inner:ident) $name:ident($t:ident: $into:ty)
+    ) => {
+        $(#[$meta])*
+        pub fn $name<$t: Into<$into>>(mut self, $name: Option<$t>) -> Self {
+            if let Some(value) = $name {
+                self.$inner.tokens.push($parent::$token(value.into()));
+            }
+
+            self
+        }
+    };
+}
+
+macro_rules! generate_token_method {
+    // Build a setter method for Entry, replacing the first token of the same kind, with a token
+    // type, a name of the function, a generic name, and a type of the expected parameter
+    (
+        $(#[$meta:meta])*
+        set $parent:ident::$token:ident $name:ident($t:ident: $into:ty)
+    ) => {
+        $(#[$meta])*
+        pub fn $name<$t: Into<$into>>(&mut self, value: $t) {
+            self.set_token($parent::$token(value.into()));
+        }
+    };
+
+    // Build a getter method for Entry, returning the value of the first token of the kind, with a
+    // token type, a name of the function, and a type of the returned reference
+    (
+        $(#[$meta:meta])*
+        get $parent:ident::$token:ident $name:ident() -> $ret:ty
+    ) => {
+        $(#[$meta])*
+        pub fn $name(&self) -> Option<$ret> {
+            self.tokens.iter().find_map(|token| match token {
+                $parent::$token(value) => Some(value.as_ref()),
+                _ => None,
+            })
+        }
+    };
 }
 
 pub(crate) use generate_builder_method;
+pub(crate) use generate_token_method;
@@ -0,0 +1,108 @@
+//! Helpers shared by the parsers of `Config` and `Entry`.
+
+use std::borrow::Cow;
+
+/// Split a line into a key and a value at the first run of spaces or tabs.
+///
+/// The value is trimmed, and is `None` if the line only contains a key.
+pub(crate) fn split_key_value(line: &str) -> (&str, Option<&str>) {
+    match line.split_once([' ', '\t']) {
+        Some((key, value)) => (key, Some(value.trim())),
+        None => (line, None),
+    }
+}
+
+/// Split an entry into trimmed lines, joining an `options` line ending with `\` with the
+/// following line, as wrapped by some editors.
+///
+/// Every line is returned along with its line number, starting from 1. A joined line has the line
+/// number of its first physical line.
+///
+/// A `\` ending the last `options` line is dropped, so that it never reaches the kernel command
+/// line.
+pub(crate) fn entry_lines(s: &str) -> Vec<(usize, Cow<'_, str>)> {
+    let mut lines: Vec<(usize, Cow<'_, str>)> = Vec::new();
+    let mut continued = false;
+
+    for (index, line) in s.lines().enumerate() {
+        let line = line.trim();
+
+        match lines.last_mut() {
+            Some((_, last)) if continued => {
+                let joined = last.to_mut();
+
+                joined.pop();
+                joined.truncate(joined.trim_end().len());
+                joined.push(' ');
+                joined.push_str(line);
+            }
+            _ => lines.push((index + 1, Cow::Borrowed(line))),
+        }
+
+        let (_, last) = lines.last().expect("a line has just been pushed");
+
+        continued = last.ends_with('\\') && split_key_value(last).0.eq_ignore_ascii_case("options");
+    }
+
+    if let (true, Some((_, last))) = (continued, lines.last_mut()) {
+        let dangling = last.to_mut();
+
+        dangling.pop();
+        dangling.truncate(dangling.trim_end().len());
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_key_value() {
+        assert_eq!(split_key_value("timeout 5"), ("timeout", Some("5")));
+        assert_eq!(split_key_value("timeout\t5"), ("timeout", Some("5")));
+        assert_eq!(split_key_value("timeout \t 5 "), ("timeout", Some("5")));
+        assert_eq!(
+            split_key_value("options root=/dev/sda1  rw"),
+            ("options", Some("root=/dev/sda1  rw"))
+        );
+        assert_eq!(split_key_value("editor"), ("editor", None));
+    }
+
+    #[test]
+    fn test_entry_lines() {
+        assert_eq!(
+            entry_lines(
+                "title AOSC OS\n options root=/dev/sda1 \\\n   rw \\\nquiet\nlinux /vmlinuz\n"
+            ),
+            vec![
+                (1, Cow::Borrowed("title AOSC OS")),
+                (2, Cow::Owned("options root=/dev/sda1 rw quiet".to_owned())),
+                (5, Cow::Borrowed("linux /vmlinuz")),
+            ]
+        );
+        assert_eq!(
+            entry_lines("linux C:\\\ninitrd /initrd\n"),
+            vec![
+                (1, Cow::Borrowed("linux C:\\")),
+                (2, Cow::Borrowed("initrd /initrd")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_lines_dangling_continuation() {
+        assert_eq!(
+            entry_lines("linux /vmlinuz\noptions quiet \\\n"),
+            vec![
+                (1, Cow::Borrowed("linux /vmlinuz")),
+                (2, Cow::Owned("options quiet".to_owned())),
+            ]
+        );
+        assert_eq!(
+            entry_lines("options quiet \\"),
+            vec![(1, Cow::Owned("options quiet".to_owned()))]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Health report of a systemd-boot installation.
+//!
+//! Create a `Report` with `SystemdBootConf::full_report()`, a `ConfSummary` with
+//! `SystemdBootConf::summary()`, a `ParseReport` with
+//! `Config::parse_with_report()` and `Entry::parse_with_report()`, or a list of `EntryLint` with
+//! `Entry::lint()`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsdbootconf::SystemdBootConf;
+//! use std::path::Path;
+//!
+//! let systemd_boot_conf = SystemdBootConf::load("/efi/loader").unwrap();
+//! let report = systemd_boot_conf.full_report(Some(Path::new("/efi")));
+//!
+//! if !report.is_healthy() {
+//!     println!("{:#?}", report);
+//! }
+//! ```
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// A problem with the loader configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ConfigIssue {
+    /// `loader.conf` does not exist in the working directory.
+    MissingConfigFile(PathBuf),
+}
+
+/// A problem with an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum EntryIssue {
+    /// A file referenced by a token does not exist on the ESP.
+    MissingFile(PathBuf),
+    /// The id cannot be used as a filename, see `Entry::validate_id()`.
+    InvalidId,
+}
+
+/// A likely mistake in an entry that does not prevent it from being loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum EntryLint {
+    /// A path does not start with `/`, although systemd-boot always resolves it from the root of
+    /// the ESP.
+    RelativePath {
+        /// The key of the token, such as `linux`.
+        key: &'static str,
+        /// The path as written.
+        path: PathBuf,
+    },
+    /// A path uses `\` as a separator instead of `/`.
+    BackslashPath {
+        /// The key of the token, such as `linux`.
+        key: &'static str,
+        /// The path as written.
+        path: PathBuf,
+    },
+}
+
+/// The state of the `default` field of the loader configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DefaultHealth {
+    /// The configuration has no `default`.
+    #[default]
+    Unset,
+    /// The `default` selects the entry with the given id.
+    Valid(String),
+    /// The `default` does not select any entry.
+    Dangling(String),
+}
+
+/// A health report of a systemd-boot installation.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    /// Problems with the loader configuration.
+    pub config_issues: Vec<ConfigIssue>,
+    /// Problems with the entries, by entry id.
+    pub entry_issues: BTreeMap<String, Vec<EntryIssue>>,
+    /// Ids of entries that would be written to the same file, by filename.
+    pub filename_collisions: BTreeMap<String, Vec<String>>,
+    /// Entry files on disk that do not belong to any entry.
+    pub orphaned_files: Vec<PathBuf>,
+    /// Groups of ids of entries with identical tokens.
+    pub duplicate_content: Vec<Vec<String>>,
+    /// The state of the default entry.
+    pub default_health: DefaultHealth,
+}
+
+impl Report {
+    /// Whether no problem has been found.
+    ///
+    /// An unset default is not considered a problem.
+    pub fn is_healthy(&self) -> bool {
+        self.config_issues.is_empty()
+            && self.entry_issues.is_empty()
+            && self.filename_collisions.is_empty()
+            && self.orphaned_files.is_empty()
+            && self.duplicate_content.is_empty()
+            && !matches!(self.default_health, DefaultHealth::Dangling(_))
+    }
+}
+
+/// A tally of the entries of a systemd-boot installation.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConfSummary {
+    /// The number of entries.
+    pub total: usize,
+    /// The number of entries booting a Linux kernel, see `EntryKind::Linux`.
+    pub linux: usize,
+    /// The number of entries running an EFI image, see `EntryKind::Efi`.
+    pub efi: usize,
+    /// The number of entries of neither kind, see `EntryKind::Unknown`.
+    pub unknown: usize,
+    /// The state of the default entry.
+    pub default_health: DefaultHealth,
+}
+
+/// A problem found while parsing a config or an entry that did not prevent it from being read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ParseWarning {
+    /// A key not modeled by this crate, kept as-is.
+    UnknownKey {
+        /// The key as written.
+        key: String,
+        /// The line number, starting from 1.
+        line: usize,
+    },
+    /// A key that may only appear once has been set again, the last value is used.
+    DuplicateKey {
+        /// The key as written on the later line.
+        key: String,
+        /// The line number of the later line, starting from 1.
+        line: usize,
+    },
+    /// A line that failed to parse and has been skipped.
+    InvalidValue {
+        /// The line number, starting from 1.
+        line: usize,
+        /// The parse error.
+        message: String,
+    },
+}
+
+/// A parsed value along with the problems found while parsing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ParseReport<T> {
+    /// The parsed value.
+    pub value: T,
+    /// The problems found, in the order of the lines.
+    pub warnings: Vec<ParseWarning>,
+}
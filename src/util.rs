@@ -0,0 +1,421 @@
+//! Miscellaneous helpers for tools that generate systemd-boot entries.
+//!
+//! # Examples
+//!
+//! ```
+//! use libsdbootconf::util::entry_id;
+//!
+//! assert_eq!(entry_id(&["aosc", "5.12.0-main"]), "aosc-5.12.0-main");
+//! ```
+
+use std::{
+    cmp::Ordering,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{self, AtomicUsize},
+};
+
+use crate::LibSDBootConfError;
+
+/// Characters that are not allowed in a FAT filename, in addition to control characters and
+/// whitespace.
+const UNSAFE_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Compute an entry id from a list of parts, e.g. a machine id or a distro name and a kernel
+/// version.
+///
+/// The parts are joined with `-`. Every character of a part that is not safe to use in a filename
+/// on the ESP (path separators, whitespace, control characters, and characters reserved by FAT) is
+/// replaced with `_`. Empty parts are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use libsdbootconf::util::entry_id;
+///
+/// assert_eq!(entry_id(&["AOSC OS", "5.12.0/main"]), "AOSC_OS-5.12.0_main");
+/// ```
+pub fn entry_id(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.chars()
+                .map(|c| {
+                    if c.is_whitespace() || c.is_control() || UNSAFE_CHARS.contains(&c) {
+                        '_'
+                    } else {
+                        c
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Compare two version strings the way humans expect, e.g. `5.9` sorts before `5.10`.
+///
+/// The strings are split into runs of digits and runs of other characters. Runs of digits are
+/// compared numerically, other runs are compared lexicographically, and a string that runs out of
+/// runs first sorts first.
+///
+/// # Examples
+///
+/// ```
+/// use libsdbootconf::util::compare_versions;
+/// use std::cmp::Ordering;
+///
+/// assert_eq!(compare_versions("5.9.0-aosc-main", "5.10.0-aosc-main"), Ordering::Less);
+/// ```
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn runs(s: &str) -> Vec<&str> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+
+        for (index, c) in s.char_indices().skip(1) {
+            let previous = s[..index].chars().next_back().unwrap_or(c);
+
+            if previous.is_ascii_digit() != c.is_ascii_digit() {
+                runs.push(&s[start..index]);
+                start = index;
+            }
+        }
+
+        if !s.is_empty() {
+            runs.push(&s[start..]);
+        }
+
+        runs
+    }
+
+    for (a, b) in runs(a).into_iter().zip(runs(b)) {
+        let ordering = if a.starts_with(|c: char| c.is_ascii_digit())
+            && b.starts_with(|c: char| c.is_ascii_digit())
+        {
+            let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        } else {
+            a.cmp(b)
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    runs(a).len().cmp(&runs(b).len())
+}
+
+/// Convert an absolute path on the system into a path relative to the root of the ESP mounted at
+/// `esp_mount`, as used by the `linux`, `initrd` and `efi` tokens.
+///
+/// The result always starts with `/`. Returns `None` if `path` is not under `esp_mount`.
+///
+/// # Examples
+///
+/// ```
+/// use libsdbootconf::util::esp_relative;
+/// use std::path::{Path, PathBuf};
+///
+/// assert_eq!(
+///     esp_relative(Path::new("/boot/efi/EFI/Linux/vmlinuz"), Path::new("/boot/efi")),
+///     Some(PathBuf::from("/EFI/Linux/vmlinuz"))
+/// );
+/// ```
+pub fn esp_relative(path: &Path, esp_mount: &Path) -> Option<PathBuf> {
+    path.strip_prefix(esp_mount)
+        .ok()
+        .map(|relative| Path::new("/").join(relative))
+}
+
+/// Generate a random machine id, as 32 lowercase hexadecimal characters.
+///
+/// Like the ids generated by systemd, it is formatted as a version 4 UUID without dashes. Only
+/// available with the `getrandom` feature.
+///
+/// # Panics
+///
+/// Panics if the operating system fails to provide random bytes.
+///
+/// # Examples
+///
+/// ```
+/// use libsdbootconf::{entry::EntryBuilder, util::generate_machine_id};
+///
+/// let entry = EntryBuilder::new("5.12.0-aosc-main")
+///     .machine_id(generate_machine_id())
+///     .build();
+///
+/// assert_eq!(entry.machine_id().unwrap().len(), 32);
+/// ```
+#[cfg(feature = "getrandom")]
+pub fn generate_machine_id() -> String {
+    let mut bytes = [0u8; 16];
+
+    getrandom::fill(&mut bytes).expect("failed to get random bytes");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Match `s` against a glob `pattern`, where `*` matches any sequence of characters and `?`
+/// matches a single character.
+pub(crate) fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let s = s.chars().collect::<Vec<_>>();
+    let (mut p, mut i) = (0, 0);
+    let mut backtrack = None;
+
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    i = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Read a file as a string, returning `LibSDBootConfError::InvalidEncoding` if it is not valid
+/// UTF-8.
+pub(crate) fn read_to_string(path: &Path) -> Result<String, LibSDBootConfError> {
+    String::from_utf8(fs::read(path)?)
+        .map_err(|_| LibSDBootConfError::InvalidEncoding(path.to_owned()))
+}
+
+/// Format `header` as comment lines, prefixing every line with `# ` unless it already starts with
+/// `#`.
+pub(crate) fn comment_header(header: &str) -> String {
+    header
+        .lines()
+        .map(|line| {
+            if line.starts_with('#') {
+                format!("{}\n", line)
+            } else {
+                format!("# {}\n", line)
+            }
+        })
+        .collect()
+}
+
+/// Write `contents` to `path` atomically.
+///
+/// The contents are written and synced to a temporary file in the same directory first, which is
+/// then renamed over `path`, so that `path` is never left truncated. The temporary file is named
+/// after `path`, the process id and a counter, and is created exclusively, so that concurrent
+/// writers never share it. On Unix the directory is synced after the rename, so that the rename
+/// survives a power loss. The temporary file is removed if anything fails.
+pub(crate) fn write_atomic<P: AsRef<Path>>(path: P, contents: &str) -> io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = path.as_ref();
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        COUNTER.fetch_add(1, atomic::Ordering::Relaxed)
+    ));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result?;
+
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_id() {
+        assert_eq!(
+            entry_id(&["0123456789abcdef", "5.12.0-aosc-main"]),
+            "0123456789abcdef-5.12.0-aosc-main"
+        );
+        assert_eq!(entry_id(&["aosc", "", "main"]), "aosc-main");
+    }
+
+    #[test]
+    fn test_entry_id_sanitized() {
+        assert_eq!(
+            entry_id(&["AOSC OS", "5.12.0/main", "a\\b\tc"]),
+            "AOSC_OS-5.12.0_main-a_b_c"
+        );
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loader.conf");
+        fs::write(&path, "timeout 3\n").unwrap();
+
+        write_atomic(&path, "timeout 5\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "timeout 5\n");
+        assert!(!dir.path().join("loader.conf.tmp").exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_atomic_cleanup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loader.conf");
+        fs::create_dir(&path).unwrap();
+
+        assert!(write_atomic(&path, "timeout 5\n").is_err());
+        assert!(!dir.path().join("loader.conf.tmp").exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_atomic_concurrent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loader.conf");
+
+        std::thread::scope(|scope| {
+            for timeout in 0..8 {
+                let path = &path;
+
+                scope.spawn(move || {
+                    for _ in 0..16 {
+                        write_atomic(path, &format!("timeout {}\n", timeout)).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert!(fs::read_to_string(&path).unwrap().starts_with("timeout "));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_comment_header() {
+        assert_eq!(
+            comment_header("Generated by aosc-installer\n# do not edit"),
+            "# Generated by aosc-installer\n# do not edit\n"
+        );
+        assert_eq!(comment_header(""), "");
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("5.9", "5.10"), Ordering::Less);
+        assert_eq!(compare_versions("5.10", "5.10"), Ordering::Equal);
+        assert_eq!(compare_versions("5.010", "5.10"), Ordering::Equal);
+        assert_eq!(compare_versions("5.10.1", "5.10"), Ordering::Greater);
+        assert_eq!(compare_versions("5.10-rc1", "5.10-rc2"), Ordering::Less);
+        assert_eq!(compare_versions("", "1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("linux-*", "linux-5.12.conf"));
+        assert!(glob_match("linux-?.??.conf", "linux-5.12.conf"));
+        assert!(glob_match("*aosc*", "5.12.0-aosc-main"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("linux-*", "windows.conf"));
+        assert!(!glob_match("linux-?", "linux-5.12"));
+        assert!(!glob_match("", "linux"));
+    }
+
+    #[test]
+    #[cfg(feature = "getrandom")]
+    fn test_generate_machine_id() {
+        let machine_id = generate_machine_id();
+
+        assert_eq!(machine_id.len(), 32);
+        assert!(machine_id
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+        assert_eq!(&machine_id[12..13], "4");
+        assert_ne!(machine_id, generate_machine_id());
+    }
+
+    #[test]
+    fn test_read_to_string() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loader.conf");
+
+        assert!(matches!(
+            read_to_string(&path),
+            Err(LibSDBootConfError::IOError(error)) if error.kind() == io::ErrorKind::NotFound
+        ));
+
+        fs::write(&path, "timeout 5\n").unwrap();
+
+        assert_eq!(read_to_string(&path).unwrap(), "timeout 5\n");
+
+        fs::write(&path, b"timeout \xff\xfe\n").unwrap();
+
+        assert!(matches!(
+            read_to_string(&path),
+            Err(LibSDBootConfError::InvalidEncoding(invalid)) if invalid == path
+        ));
+    }
+
+    #[test]
+    fn test_esp_relative() {
+        let esp = Path::new("/boot/efi");
+
+        assert_eq!(
+            esp_relative(Path::new("/boot/efi/EFI/Linux/vmlinuz"), esp),
+            Some(PathBuf::from("/EFI/Linux/vmlinuz"))
+        );
+        assert_eq!(
+            esp_relative(
+                Path::new("/boot/efi/intel-ucode.img"),
+                Path::new("/boot/efi/")
+            ),
+            Some(PathBuf::from("/intel-ucode.img"))
+        );
+        assert_eq!(esp_relative(esp, esp), Some(PathBuf::from("/")));
+        assert_eq!(esp_relative(Path::new("/boot/vmlinuz"), esp), None);
+        assert_eq!(esp_relative(Path::new("/boot/efi2/vmlinuz"), esp), None);
+        assert_eq!(esp_relative(Path::new("EFI/Linux/vmlinuz"), esp), None);
+    }
+}